@@ -0,0 +1,7 @@
+//! A UCI engine on standard input and output, for chess GUIs and tournament runners.
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    wingmyr_chess::run_uci(io::stdin().lock(), io::stdout())
+}
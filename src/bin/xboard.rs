@@ -0,0 +1,7 @@
+//! An XBoard (CECP) engine on standard input and output, for tools that don't speak UCI.
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    wingmyr_chess::run_xboard(io::stdin().lock(), io::stdout())
+}
@@ -0,0 +1,326 @@
+//! The board as bitboards: one 64 bit number per colour and kind of piece, with bit `i` set when
+//! such a piece stands on board position `i`. Knight, king and pawn attacks are looked up in tables
+//! built at compile time, sliding pieces follow precomputed rays up to the first blocker.
+
+use crate::{Colour, Piece};
+
+/// (file, rank) steps along files and ranks
+const STRAIGHT_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+/// (file, rank) steps along diagonals
+const DIAGONAL_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+/// (file, rank) jumps of a knight
+const KNIGHT_STEPS: [(i32, i32); 8] = [
+    (1, 2),
+    (-1, 2),
+    (2, 1),
+    (-2, 1),
+    (1, -2),
+    (-1, -2),
+    (2, -1),
+    (-2, -1),
+];
+/// (file, rank) steps of a king
+const KING_STEPS: [(i32, i32); 8] = [
+    (0, 1),
+    (0, -1),
+    (1, 0),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+static KNIGHT_ATTACKS: [u64; 64] = step_table(&KNIGHT_STEPS);
+static KING_ATTACKS: [u64; 64] = step_table(&KING_STEPS);
+/// the squares a white (0) or black (1) pawn attacks
+static PAWN_ATTACKS: [[u64; 64]; 2] = [
+    step_table(&[(1, 1), (-1, 1)]),
+    step_table(&[(1, -1), (-1, -1)]),
+];
+static STRAIGHT_RAYS: [[u64; 64]; 4] = ray_tables(&STRAIGHT_DIRECTIONS);
+static DIAGONAL_RAYS: [[u64; 64]; 4] = ray_tables(&DIAGONAL_DIRECTIONS);
+
+/// the position one step away, if it's still on the board
+const fn step(position: usize, (file_step, rank_step): (i32, i32)) -> Option<usize> {
+    let file = (position % 8) as i32 + file_step;
+    let rank = (position / 8) as i32 + rank_step;
+    if file < 0 || file > 7 || rank < 0 || rank > 7 {
+        None
+    } else {
+        Some((rank * 8 + file) as usize)
+    }
+}
+
+/// for every position, the squares reached with a single step of each kind
+const fn step_table(steps: &[(i32, i32)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut position = 0;
+    while position < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            if let Some(target) = step(position, steps[i]) {
+                table[position] |= 1 << target;
+            }
+            i += 1;
+        }
+        position += 1;
+    }
+    table
+}
+
+/// for every direction and position, the squares up to the edge of the board
+const fn ray_tables(directions: &[(i32, i32); 4]) -> [[u64; 64]; 4] {
+    let mut tables = [[0; 64]; 4];
+    let mut direction = 0;
+    while direction < 4 {
+        let mut position = 0;
+        while position < 64 {
+            let mut current = step(position, directions[direction]);
+            while let Some(target) = current {
+                tables[direction][position] |= 1 << target;
+                current = step(target, directions[direction]);
+            }
+            position += 1;
+        }
+        direction += 1;
+    }
+    tables
+}
+
+/// the squares a slider reaches along the rays, stopping at (and including) the first piece
+fn slide(
+    rays: &[[u64; 64]; 4],
+    directions: &[(i32, i32); 4],
+    position: usize,
+    occupied: u64,
+) -> u64 {
+    let mut attacks = 0;
+    for (direction, (file_step, rank_step)) in directions.iter().enumerate() {
+        let ray = rays[direction][position];
+        let blockers = ray & occupied;
+        if blockers == 0 {
+            attacks |= ray;
+            continue;
+        }
+        // rays going up the board meet the lowest blocker first, rays going down the highest
+        let blocker = if rank_step * 8 + file_step > 0 {
+            blockers.trailing_zeros()
+        } else {
+            63 - blockers.leading_zeros()
+        };
+        attacks |= ray ^ rays[direction][blocker as usize];
+    }
+    attacks
+}
+
+pub(crate) fn knight_attacks(position: usize) -> u64 {
+    KNIGHT_ATTACKS[position]
+}
+
+pub(crate) fn king_attacks(position: usize) -> u64 {
+    KING_ATTACKS[position]
+}
+
+pub(crate) fn pawn_attacks(position: usize, colour: Colour) -> u64 {
+    PAWN_ATTACKS[colour_index(colour)][position]
+}
+
+pub(crate) fn rook_attacks(position: usize, occupied: u64) -> u64 {
+    slide(&STRAIGHT_RAYS, &STRAIGHT_DIRECTIONS, position, occupied)
+}
+
+pub(crate) fn bishop_attacks(position: usize, occupied: u64) -> u64 {
+    slide(&DIAGONAL_RAYS, &DIAGONAL_DIRECTIONS, position, occupied)
+}
+
+/// Iterates over the positions of the set bits, lowest first.
+pub(crate) struct Positions(pub(crate) u64);
+
+impl Iterator for Positions {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let position = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(position)
+    }
+}
+
+/// The pieces of both colours, kept alongside `Game.board`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Bitboards {
+    /// indexed by colour, then kind of piece
+    pieces: [[u64; 6]; 2],
+    colours: [u64; 2],
+}
+
+impl Bitboards {
+    pub(crate) fn from_board(board: &[Option<Piece>; 64]) -> Bitboards {
+        let mut bitboards = Bitboards::default();
+        for (position, piece) in board.iter().enumerate() {
+            bitboards.set(position, *piece);
+        }
+        bitboards
+    }
+
+    /// puts a piece on a square, or empties it
+    pub(crate) fn set(&mut self, position: usize, piece: Option<Piece>) {
+        let bit = 1 << position;
+        for colour in 0..2 {
+            if self.colours[colour] & bit != 0 {
+                self.colours[colour] &= !bit;
+                for kind in self.pieces[colour].iter_mut() {
+                    *kind &= !bit;
+                }
+            }
+        }
+
+        if let Some(piece) = piece {
+            let (colour, kind) = piece_index(piece);
+            self.pieces[colour][kind] |= bit;
+            self.colours[colour] |= bit;
+        }
+    }
+
+    /// every piece of the given kind and colour, e.g. `pieces(Piece::Rook(Colour::White))`
+    pub(crate) fn pieces(&self, piece: Piece) -> u64 {
+        let (colour, kind) = piece_index(piece);
+        self.pieces[colour][kind]
+    }
+
+    /// the piece standing on a position, if any
+    pub(crate) fn piece_at(&self, position: usize) -> Option<Piece> {
+        let bit = 1 << position;
+        let colour = if self.colours[0] & bit != 0 {
+            Colour::White
+        } else if self.colours[1] & bit != 0 {
+            Colour::Black
+        } else {
+            return None;
+        };
+        [
+            Piece::King(colour),
+            Piece::Queen(colour),
+            Piece::Rook(colour),
+            Piece::Bishop(colour),
+            Piece::Knight(colour),
+            Piece::Pawn(colour),
+        ]
+        .into_iter()
+        .find(|&piece| self.pieces(piece) & bit != 0)
+    }
+
+    pub(crate) fn colour(&self, colour: Colour) -> u64 {
+        self.colours[colour_index(colour)]
+    }
+
+    pub(crate) fn occupied(&self) -> u64 {
+        self.colours[0] | self.colours[1]
+    }
+
+    /// returns true if a piece of colour `by` attacks the position
+    pub(crate) fn attacked(&self, position: usize, by: Colour) -> bool {
+        let defender = match by {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        };
+        let occupied = self.occupied();
+        let straight = self.pieces(Piece::Rook(by)) | self.pieces(Piece::Queen(by));
+        let diagonal = self.pieces(Piece::Bishop(by)) | self.pieces(Piece::Queen(by));
+
+        // looking from the attacked square, with the moves of each kind of piece
+        knight_attacks(position) & self.pieces(Piece::Knight(by)) != 0
+            || king_attacks(position) & self.pieces(Piece::King(by)) != 0
+            || pawn_attacks(position, defender) & self.pieces(Piece::Pawn(by)) != 0
+            || rook_attacks(position, occupied) & straight != 0
+            || bishop_attacks(position, occupied) & diagonal != 0
+    }
+
+    /// returns true if the king of the given colour is attacked, false if there is no king
+    pub(crate) fn in_check(&self, colour: Colour) -> bool {
+        let king = self.pieces(Piece::King(colour));
+        let other = match colour {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        };
+        king != 0 && self.attacked(king.trailing_zeros() as usize, other)
+    }
+}
+
+pub(crate) fn colour_index(colour: Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+    }
+}
+
+/// the colour and kind indexes used for the `pieces` table
+pub(crate) fn piece_index(piece: Piece) -> (usize, usize) {
+    match piece {
+        Piece::King(colour) => (colour_index(colour), 0),
+        Piece::Queen(colour) => (colour_index(colour), 1),
+        Piece::Rook(colour) => (colour_index(colour), 2),
+        Piece::Bishop(colour) => (colour_index(colour), 3),
+        Piece::Knight(colour) => (colour_index(colour), 4),
+        Piece::Pawn(colour) => (colour_index(colour), 5),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bishop_attacks, knight_attacks, rook_attacks, Bitboards, Positions};
+    use crate::{Colour, Game, Piece, Square};
+
+    fn squares(bitboard: u64) -> Vec<String> {
+        let mut squares: Vec<String> = Positions(bitboard)
+            .map(|position| Square::from_index(position).unwrap().to_string())
+            .collect();
+        squares.sort();
+        squares
+    }
+
+    fn bit(square: &str) -> u64 {
+        1 << Square::parse(square).unwrap().index()
+    }
+
+    #[test]
+    fn attack_tables() {
+        let a1 = Square::parse("a1").unwrap().index();
+        assert_eq!(squares(knight_attacks(a1)), ["b3", "c2"]);
+
+        let d4 = Square::parse("d4").unwrap().index();
+        assert_eq!(rook_attacks(d4, 0).count_ones(), 14);
+        assert_eq!(bishop_attacks(d4, 0).count_ones(), 13);
+
+        // blockers are included, the squares behind them aren't
+        let blockers = bit("d6") | bit("b4") | bit("f2");
+        assert_eq!(
+            squares(rook_attacks(d4, blockers)),
+            ["b4", "c4", "d1", "d2", "d3", "d5", "d6", "e4", "f4", "g4", "h4"]
+        );
+        assert_eq!(
+            squares(bishop_attacks(d4, blockers)),
+            ["a1", "a7", "b2", "b6", "c3", "c5", "e3", "e5", "f2", "f6", "g7", "h8"]
+        );
+    }
+
+    #[test]
+    fn matches_the_board() {
+        let game = Game::new();
+        let bitboards = Bitboards::from_board(&game.board);
+        assert_eq!(bitboards.occupied().count_ones(), 32);
+        assert_eq!(
+            squares(bitboards.pieces(Piece::Knight(Colour::Black))),
+            ["b8", "g8"]
+        );
+        assert_eq!(bitboards, game.bitboards);
+
+        assert!(bitboards.attacked(Square::parse("f3").unwrap().index(), Colour::White));
+        assert!(!bitboards.attacked(Square::parse("e4").unwrap().index(), Colour::White));
+        assert!(!bitboards.in_check(Colour::White));
+    }
+}
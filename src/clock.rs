@@ -0,0 +1,537 @@
+//! Chess clocks for timed games.
+//!
+//! A `Clock` is set up with a `TimeControl` and a `TimeSource`, and attached to a game with
+//! `Game::set_clock`. From then on every move presses the clock, and a player whose flag falls
+//! loses on time, or draws when the opponent can't checkmate anymore.
+//!
+//! ```text
+//! 40/90 + 30 + 30s: 90 minutes for 40 moves, then 30 minutes for the rest of the game,
+//!                   with 30 seconds added after every move from the start
+//! ```
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{ChessError, Colour, Game, GameState};
+
+/// Tells the clock what time it is, as the time passed since some fixed point.
+/// Tests use `ManualTime` to decide for themselves how much time goes by.
+pub trait TimeSource: Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+/// The time on the computer's monotonic clock, counted from when it was created.
+#[derive(Copy, Clone, Debug)]
+pub struct MonotonicTime {
+    start: Instant,
+}
+
+impl MonotonicTime {
+    pub fn new() -> MonotonicTime {
+        MonotonicTime {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicTime {
+    fn default() -> Self {
+        MonotonicTime::new()
+    }
+}
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Time that only moves when told to. Clones share the same time, so one can be given to
+/// the clock while another is used to move time forward.
+#[derive(Clone, Debug, Default)]
+pub struct ManualTime {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualTime {
+    pub fn new() -> ManualTime {
+        ManualTime::default()
+    }
+
+    /// Moves time forward.
+    pub fn advance(&self, time: Duration) {
+        *self.now.lock().unwrap() += time;
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// What a player gets back for each move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Bonus {
+    /// nothing, the time only goes down (sudden death)
+    None,
+    /// the time is added after every move (Fischer increment)
+    Increment(Duration),
+    /// the time used for a move is given back, up to this much (Bronstein delay)
+    Bronstein(Duration),
+    /// the clock only starts running this long after the turn starts (simple delay)
+    Delay(Duration),
+}
+
+/// A period of a time control.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Stage {
+    /// the number of moves to make in this stage, `None` for the rest of the game
+    pub moves: Option<u32>,
+    /// the time added to the clock when the stage starts
+    pub time: Duration,
+    pub bonus: Bonus,
+}
+
+/// The time each player gets for the game, made of one or more stages.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    /// A fixed amount of time for the whole game.
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl::single(time, Bonus::None)
+    }
+
+    /// `increment` is added after every move.
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl::single(time, Bonus::Increment(increment))
+    }
+
+    /// The time used for each move is given back, up to `delay`.
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, Bonus::Bronstein(delay))
+    }
+
+    /// The clock waits `delay` before it starts running on each move.
+    pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, Bonus::Delay(delay))
+    }
+
+    /// Several stages played one after the other. The last stage should have `moves: None`,
+    /// otherwise it's repeated once its moves are made.
+    pub fn stages(stages: Vec<Stage>) -> TimeControl {
+        TimeControl { stages }
+    }
+
+    fn single(time: Duration, bonus: Bonus) -> TimeControl {
+        TimeControl {
+            stages: vec![Stage {
+                moves: None,
+                time,
+                bonus,
+            }],
+        }
+    }
+}
+
+/// A clock for both players, only one of which runs at a time.
+#[derive(Clone)]
+pub struct Clock {
+    control: TimeControl,
+    source: Arc<dyn TimeSource>,
+
+    /// the time left for white and black, not counting the turn that's running
+    remaining: [Duration; 2],
+    /// moves made in the current stage
+    moves: [u32; 2],
+    stage: [usize; 2],
+
+    /// the colour whose clock is running and when its turn started
+    running: Option<(Colour, Duration)>,
+    /// the colour that ran out of time
+    flagged: Option<Colour>,
+}
+
+impl Clock {
+    /// A clock with the first stage's time on both sides, that isn't running yet.
+    pub fn new(control: TimeControl, source: impl TimeSource + 'static) -> Clock {
+        let time = control
+            .stages
+            .first()
+            .map(|stage| stage.time)
+            .unwrap_or_default();
+        Clock {
+            control,
+            source: Arc::new(source),
+            remaining: [time; 2],
+            moves: [0; 2],
+            stage: [0; 2],
+            running: None,
+            flagged: None,
+        }
+    }
+
+    /// Starts the clock of `colour`, stopping the other one without ending its turn.
+    pub fn start(&mut self, colour: Colour) {
+        if self.flagged.is_none() {
+            self.running = Some((colour, self.source.now()));
+        }
+    }
+
+    /// Stops the clock, keeping the time used on the current turn.
+    pub fn stop(&mut self) {
+        if let Some((colour, _)) = self.running {
+            self.remaining[index(colour)] = self.get_remaining(colour);
+            self.running = None;
+        }
+    }
+
+    /// Ends the turn of the colour whose clock is running and starts the other clock.
+    /// Returns false, without starting the other clock, if the flag had already fallen.
+    pub fn press(&mut self) -> bool {
+        let (colour, started) = match self.running {
+            Some(running) => running,
+            None => return self.flagged.is_none(),
+        };
+        let now = self.source.now();
+        if self.get_flagged().is_some() {
+            self.flagged = Some(colour);
+            self.running = None;
+            return false;
+        }
+
+        let used = now.saturating_sub(started);
+        let stage = self.current_stage(colour);
+        let side = index(colour);
+        self.remaining[side] = self.remaining[side].saturating_sub(charged(used, stage.bonus));
+        self.remaining[side] += match stage.bonus {
+            Bonus::Increment(increment) => increment,
+            Bonus::Bronstein(delay) => used.min(delay),
+            Bonus::None | Bonus::Delay(_) => Duration::ZERO,
+        };
+
+        // on to the next stage once this one's moves are made
+        self.moves[side] += 1;
+        if stage.moves == Some(self.moves[side]) {
+            self.moves[side] = 0;
+            self.stage[side] = (self.stage[side] + 1).min(self.control.stages.len() - 1);
+            self.remaining[side] += self.current_stage(colour).time;
+        }
+
+        self.running = Some((other(colour), now));
+        true
+    }
+
+    /// Returns the time `colour` has left, including the turn that's running.
+    pub fn get_remaining(&self, colour: Colour) -> Duration {
+        let remaining = self.remaining[index(colour)];
+        match self.running {
+            Some((running, started)) if running == colour => {
+                let used = self.source.now().saturating_sub(started);
+                remaining.saturating_sub(charged(used, self.current_stage(colour).bonus))
+            }
+            _ => remaining,
+        }
+    }
+
+    /// Returns the colour whose flag has fallen, if any.
+    pub fn get_flagged(&self) -> Option<Colour> {
+        self.flagged.or(match self.running {
+            Some((colour, _)) if self.get_remaining(colour).is_zero() => Some(colour),
+            _ => None,
+        })
+    }
+
+    /// Returns the colour whose clock is running.
+    pub fn get_running(&self) -> Option<Colour> {
+        self.running.map(|(colour, _)| colour)
+    }
+
+    fn current_stage(&self, colour: Colour) -> Stage {
+        self.control
+            .stages
+            .get(self.stage[index(colour)])
+            .copied()
+            .unwrap_or(Stage {
+                moves: None,
+                time: Duration::ZERO,
+                bonus: Bonus::None,
+            })
+    }
+}
+
+impl Game {
+    /// Attaches a clock to the game and starts it for the side to move.
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        if !self.is_game_over() {
+            clock.start(self.active_colour);
+        }
+        self.clock = Some(clock);
+    }
+
+    /// Returns the clock attached to the game, if any.
+    pub fn get_clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    /// Ends the game on time if the flag of the side to move has fallen, and returns the game state.
+    /// Moves check the clock as well, this is for noticing a timeout while waiting for a move.
+    pub fn check_clock(&mut self) -> GameState {
+        let flagged = self.clock.as_ref().and_then(|clock| clock.get_flagged());
+        if let Some(colour) = flagged {
+            let _ = self.time_out(colour);
+        }
+        self.state
+    }
+
+    /// presses the clock for a move, returns an error and ends the game if the mover ran out of time
+    pub(crate) fn press_clock(&mut self) -> Result<(), ChessError> {
+        let in_time = match &mut self.clock {
+            Some(clock) => clock.press(),
+            None => true,
+        };
+        if !in_time {
+            self.check_clock();
+            return Err(ChessError::GameOver);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn stop_clock(&mut self) {
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+    }
+
+    /// hands the clock to the side to move after a move was taken back, which also brings a
+    /// finished game back to life
+    pub(crate) fn restart_clock(&mut self) {
+        let game_over = self.is_game_over();
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+            if !game_over {
+                clock.start(self.active_colour);
+            }
+        }
+    }
+}
+
+/// the time taken off the clock for a turn that took `used`
+fn charged(used: Duration, bonus: Bonus) -> Duration {
+    match bonus {
+        Bonus::Delay(delay) => used.saturating_sub(delay),
+        _ => used,
+    }
+}
+
+fn index(colour: Colour) -> usize {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+    }
+}
+
+fn other(colour: Colour) -> Colour {
+    match colour {
+        Colour::White => Colour::Black,
+        Colour::Black => Colour::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Bonus, Clock, ManualTime, Stage, TimeControl};
+    use crate::{ChessError, Colour, Game, GameState, Termination};
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn running_clock(control: TimeControl) -> (Clock, ManualTime) {
+        let time = ManualTime::new();
+        let mut clock = Clock::new(control, time.clone());
+        clock.start(Colour::White);
+        (clock, time)
+    }
+
+    #[test]
+    fn sudden_death() {
+        let (mut clock, time) = running_clock(TimeControl::sudden_death(minutes(5)));
+        time.advance(seconds(10));
+        assert_eq!(clock.get_remaining(Colour::White), seconds(290));
+        assert!(clock.press());
+        assert_eq!(clock.get_running(), Some(Colour::Black));
+
+        time.advance(minutes(5));
+        assert_eq!(clock.get_flagged(), Some(Colour::Black));
+        assert!(!clock.press());
+        assert_eq!(clock.get_remaining(Colour::White), seconds(290));
+    }
+
+    #[test]
+    fn fischer_increment() {
+        let (mut clock, time) = running_clock(TimeControl::fischer(minutes(3), seconds(2)));
+        time.advance(seconds(5));
+        clock.press();
+        assert_eq!(clock.get_remaining(Colour::White), seconds(177));
+    }
+
+    #[test]
+    fn bronstein_delay() {
+        let (mut clock, time) = running_clock(TimeControl::bronstein(minutes(3), seconds(5)));
+        // a fast move gets all its time back, a slow one only the delay
+        time.advance(seconds(3));
+        clock.press();
+        assert_eq!(clock.get_remaining(Colour::White), minutes(3));
+
+        time.advance(seconds(8));
+        clock.press();
+        assert_eq!(clock.get_remaining(Colour::Black), seconds(177));
+    }
+
+    #[test]
+    fn simple_delay() {
+        let (mut clock, time) = running_clock(TimeControl::simple_delay(minutes(3), seconds(5)));
+        time.advance(seconds(4));
+        assert_eq!(clock.get_remaining(Colour::White), minutes(3));
+        time.advance(seconds(4));
+        assert_eq!(clock.get_remaining(Colour::White), seconds(177));
+        clock.press();
+        assert_eq!(clock.get_remaining(Colour::White), seconds(177));
+    }
+
+    #[test]
+    fn stages() {
+        // 40/90 + 30 + 30s, shortened to 2 moves for the first stage
+        let increment = Bonus::Increment(seconds(30));
+        let (mut clock, time) = running_clock(TimeControl::stages(vec![
+            Stage {
+                moves: Some(2),
+                time: minutes(90),
+                bonus: increment,
+            },
+            Stage {
+                moves: None,
+                time: minutes(30),
+                bonus: increment,
+            },
+        ]));
+
+        for _ in 0..2 {
+            time.advance(minutes(10));
+            clock.press(); // white
+            time.advance(minutes(1));
+            clock.press(); // black
+        }
+        assert_eq!(clock.get_remaining(Colour::White), minutes(71 + 30));
+        assert_eq!(clock.get_remaining(Colour::Black), minutes(89 + 30));
+
+        time.advance(minutes(1));
+        clock.press();
+        assert_eq!(
+            clock.get_remaining(Colour::White),
+            minutes(100) + seconds(30)
+        );
+    }
+
+    #[test]
+    fn timeout_ends_the_game() {
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(minutes(1)),
+            time.clone(),
+        ));
+
+        time.advance(seconds(30));
+        assert!(game.make_san_move("e4").is_some());
+        time.advance(seconds(61));
+        assert_eq!(game.check_clock(), GameState::GameOver);
+
+        let outcome = game.get_outcome().unwrap();
+        assert_eq!(outcome.winner, Some(Colour::White));
+        assert_eq!(outcome.termination, Termination::Timeout);
+
+        // moving too late loses as well
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(minutes(1)),
+            time.clone(),
+        ));
+        time.advance(seconds(61));
+        assert_eq!(game.try_make_san_move("e4"), Err(ChessError::GameOver));
+        assert_eq!(game.get_outcome().unwrap().winner, Some(Colour::Black));
+    }
+
+    #[test]
+    fn timeout_against_a_lone_king() {
+        let time = ManualTime::new();
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(minutes(1)),
+            time.clone(),
+        ));
+
+        time.advance(seconds(61));
+        assert_eq!(game.check_clock(), GameState::Draw);
+        assert_eq!(game.get_outcome().unwrap().winner, None);
+    }
+
+    #[test]
+    fn claimed_draw_stops_the_clock() {
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(minutes(1)),
+            time.clone(),
+        ));
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"] {
+            game.make_san_move(san);
+        }
+        assert_eq!(game.claim_draw(), Ok(GameState::Repetition));
+
+        time.advance(minutes(2));
+        let clock = game.get_clock().unwrap();
+        assert_eq!(clock.get_running(), None);
+        assert_eq!(clock.get_flagged(), None);
+        assert_eq!(game.check_clock(), GameState::Repetition);
+    }
+
+    #[test]
+    fn undo_hands_the_clock_back() {
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(minutes(1)),
+            time.clone(),
+        ));
+
+        time.advance(seconds(10));
+        game.make_san_move("e4");
+        time.advance(seconds(5));
+        game.undo_move();
+        time.advance(seconds(30));
+        let clock = game.get_clock().unwrap();
+        assert_eq!(clock.get_running(), Some(Colour::White));
+        assert_eq!(clock.get_remaining(Colour::White), seconds(20));
+        assert_eq!(clock.get_remaining(Colour::Black), seconds(55));
+
+        // taking back a checkmate starts the clock again
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san);
+        }
+        assert_eq!(game.get_clock().unwrap().get_running(), None);
+        game.undo_move();
+        assert_eq!(game.get_clock().unwrap().get_running(), Some(Colour::Black));
+    }
+}
@@ -0,0 +1,300 @@
+//! Draws by the rules rather than by checkmate or stalemate: repeating a position,
+//! the fifty and seventy-five move rules and positions without enough material to checkmate.
+
+use crate::{ChessError, Colour, Game, GameState, Piece};
+
+impl Game {
+    /// Returns how many times the current position has come up in this game, counting the
+    /// current one. Positions count as the same when the same pieces stand on the same squares,
+    /// the same side is to move and the same castling and en passant captures are possible.
+    pub fn get_repetition_count(&self) -> usize {
+        let hash = self.hash;
+        // a capture or pawn move can't be undone, so only the positions since the last one can repeat
+        let repeated = self
+            .history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .filter(|entry| entry.position_hash == hash)
+            .count();
+        repeated + 1
+    }
+
+    /// Returns the number of half moves since the last capture or pawn move.
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// Returns the number of the current move, which starts at 1 and goes up after every move by black.
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Returns true if the side to move may claim a draw, because the position came up three times
+    /// or fifty moves by each side went by without a capture or pawn move.
+    pub fn can_claim_draw(&self) -> bool {
+        !self.is_game_over() && (self.get_repetition_count() >= 3 || self.halfmove_clock >= 100)
+    }
+
+    /// Ends the game in a draw if `can_claim_draw` allows it and returns the new state.
+    pub fn claim_draw(&mut self) -> Result<GameState, ChessError> {
+        if self.is_game_over() {
+            return Err(ChessError::GameOver);
+        }
+        self.state = if self.get_repetition_count() >= 3 {
+            GameState::Repetition
+        } else if self.halfmove_clock >= 100 {
+            GameState::FiftyMoveRule
+        } else {
+            return Err(ChessError::NoDrawToClaim);
+        };
+        self.stop_clock();
+        Ok(self.state)
+    }
+
+    /// Returns true if `colour` can't checkmate with any series of legal moves, whatever the other
+    /// side does. A player who runs out of time in such a position doesn't lose, the game is drawn.
+    pub fn has_insufficient_material(&self, colour: Colour) -> bool {
+        let own = self.pieces_besides_king(colour);
+        let other = self.pieces_besides_king(match colour {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        });
+
+        match own.as_slice() {
+            [] => true,
+            // a lone knight can only mate with help from enemy pieces blocking the king in
+            [(Piece::Knight(_), _)] => other.is_empty(),
+            _ => {
+                // bishops that all stand on one square colour can only mate if the enemy king is
+                // blocked by a piece that can move onto the other colour, or by a pawn or knight
+                let square_colour = own[0].1;
+                own.iter().chain(other.iter()).all(|&(piece, square)| {
+                    matches!(piece, Piece::Bishop(_)) && square_colour == square
+                })
+            }
+        }
+    }
+
+    /// draws that end the game without anyone claiming them
+    pub(crate) fn automatic_draw(&self) -> Option<GameState> {
+        if self.get_repetition_count() >= 5 {
+            Some(GameState::Repetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(GameState::FiftyMoveRule)
+        } else if self.has_insufficient_material(Colour::White)
+            && self.has_insufficient_material(Colour::Black)
+        {
+            Some(GameState::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    /// every piece of a colour other than the king, with the colour of the square it stands on
+    /// (`true` for light squares)
+    fn pieces_besides_king(&self, colour: Colour) -> Vec<(Piece, bool)> {
+        (0..64)
+            .filter_map(|position: usize| match self.board[position] {
+                Some(Piece::King(_)) | None => None,
+                Some(piece) if Game::colour_of(Some(piece)) == Some(colour) => {
+                    Some((piece, (position % 8 + position / 8).is_multiple_of(2)))
+                }
+                Some(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChessError, Colour, Game, GameState};
+
+    /// both knights go out and come back, repeating the starting position
+    fn shuffle_knights(game: &mut Game) {
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+            game.make_san_move(san);
+        }
+    }
+
+    #[test]
+    fn threefold_repetition_claim() {
+        let mut game = Game::new();
+        assert_eq!(game.get_repetition_count(), 1);
+        assert_eq!(game.claim_draw(), Err(ChessError::NoDrawToClaim));
+
+        shuffle_knights(&mut game);
+        assert_eq!(game.get_repetition_count(), 2);
+        assert!(!game.can_claim_draw());
+
+        shuffle_knights(&mut game);
+        assert_eq!(game.get_repetition_count(), 3);
+        assert!(game.can_claim_draw());
+
+        assert_eq!(game.claim_draw(), Ok(GameState::Repetition));
+        assert!(game.is_game_over());
+        assert_eq!(game.make_move("e2", "e4"), None);
+    }
+
+    #[test]
+    fn fivefold_repetition_ends_the_game() {
+        let mut game = Game::new();
+        for _ in 0..3 {
+            shuffle_knights(&mut game);
+        }
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+
+        shuffle_knights(&mut game);
+        assert_eq!(game.get_repetition_count(), 5);
+        assert_eq!(game.get_game_state(), GameState::Repetition);
+        assert!(game.is_game_over());
+
+        // taking back the last move goes back to a game in progress
+        game.undo_move();
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+    }
+
+    #[test]
+    fn irrelevant_en_passant_square() {
+        // after 1. e4 no black pawn can take on e3, so the position repeats after Nf6 Nf3 Ng8 Ng1
+        let mut game = Game::new();
+        game.make_san_move("e4");
+        let position = game.hash();
+        for san in ["Nf6", "Nf3", "Ng8", "Ng1"] {
+            game.make_san_move(san);
+        }
+        assert_eq!(game.hash(), position);
+        assert_eq!(game.get_repetition_count(), 2);
+
+        // a black pawn on d4 can take en passant, which makes it a different position
+        let mut game = Game::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let with_capture = game.hash();
+        game.en_passant = None;
+        assert_ne!(game.compute_hash(), with_capture);
+
+        let mut game = Game::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        let without_capture = game.hash();
+        game.en_passant = None;
+        assert_eq!(game.compute_hash(), without_capture);
+    }
+
+    #[test]
+    fn captures_reset_repetitions() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4K3/R6r w - - 0 1").unwrap();
+        game.make_san_move("Rxh1");
+        assert_eq!(game.get_repetition_count(), 1);
+    }
+
+    #[test]
+    fn counters() {
+        let mut game = Game::new();
+        assert_eq!(
+            (game.get_halfmove_clock(), game.get_fullmove_number()),
+            (0, 1)
+        );
+        for san in ["Nf3", "Nc6", "Ng5"] {
+            game.make_san_move(san);
+        }
+        assert_eq!(
+            (game.get_halfmove_clock(), game.get_fullmove_number()),
+            (3, 2)
+        );
+        game.make_san_move("e5");
+        assert_eq!(
+            (game.get_halfmove_clock(), game.get_fullmove_number()),
+            (0, 3)
+        );
+
+        // and they survive a trip through FEN
+        let copy = Game::from_fen(&game.to_fen()).unwrap();
+        assert_eq!(copy.get_halfmove_clock(), 0);
+        assert_eq!(copy.get_fullmove_number(), 3);
+        game.undo_move();
+        assert_eq!(
+            (game.get_halfmove_clock(), game.get_fullmove_number()),
+            (3, 2)
+        );
+    }
+
+    #[test]
+    fn fifty_move_claim() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert!(!game.can_claim_draw());
+        assert_eq!(game.claim_draw(), Err(ChessError::NoDrawToClaim));
+
+        game.make_san_move("Ra2");
+        assert_eq!(game.get_halfmove_clock(), 100);
+        assert!(game.can_claim_draw());
+        assert_eq!(game.claim_draw(), Ok(GameState::FiftyMoveRule));
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn seventy_five_move_rule() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 120").unwrap();
+        game.make_san_move("Ra2");
+        assert_eq!(game.get_game_state(), GameState::FiftyMoveRule);
+        assert_eq!(game.make_san_move("Kd8"), None);
+
+        // checkmate on the last move still counts
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/3RK3 w - - 149 120").unwrap();
+        assert_eq!(game.make_san_move("Rd8#"), Some(GameState::CheckMate));
+    }
+
+    #[test]
+    fn dead_positions() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1",
+            // bishops on the same square colour, even on both sides
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/1B6/2B1K3 w - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(
+                game.get_game_state(),
+                GameState::InsufficientMaterial,
+                "{}",
+                fen
+            );
+        }
+
+        for fen in [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+            // bishops on different square colours
+            "4k3/8/8/8/8/8/8/1BB1K3 w - - 0 1",
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            // a knight against a knight can mate with help
+            "4kn2/8/8/8/8/8/8/1N2K3 w - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.get_game_state(), GameState::InProgress, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn capturing_the_last_piece() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            game.make_san_move("Kxe2"),
+            Some(GameState::InsufficientMaterial)
+        );
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn insufficient_material_for_one_side() {
+        // a rook against a lone king, white can still win but black can't
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(!game.has_insufficient_material(Colour::White));
+        assert!(game.has_insufficient_material(Colour::Black));
+
+        // a knight can mate a king that is blocked in by its own pawn
+        let game = Game::from_fen("4k3/p7/8/8/8/8/8/1N2K3 w - - 0 1").unwrap();
+        assert!(!game.has_insufficient_material(Colour::White));
+        assert!(!game.has_insufficient_material(Colour::Black));
+    }
+}
@@ -0,0 +1,725 @@
+//! A built-in opponent: iterative deepening alpha-beta search (negamax) with quiescence search,
+//! a transposition table, and moves ordered by the transposition table move, captures (most
+//! valuable victim, least valuable attacker), killer moves and the history heuristic.
+//!
+//! ```
+//! use wingmyr_chess::{Engine, Game, SearchLimits};
+//!
+//! let game = Game::new();
+//! let mut engine = Engine::new();
+//! let result = engine.search(&game, SearchLimits::depth(3));
+//! assert!(result.best_move.is_some());
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::bitboard::{colour_index, piece_index};
+use crate::movegen::MAX_MOVES;
+use crate::{Colour, Game, GameState, MonotonicTime, Move, MoveList, Piece, TimeSource, Weights};
+
+/// The deepest the search goes, counted in half moves from the root.
+const MAX_PLY: usize = 64;
+/// The score of giving checkmate right now, mates further away score a little less.
+const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+/// How often the time is looked at, in nodes.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+/// Moves a game is expected to last from here, when the time control doesn't say how many are
+/// left until the next one
+const MOVES_TO_GO: u32 = 30;
+
+/// The material value of each kind of piece in centipawns, in the order of `Bitboards`.
+const PIECE_VALUES: [i32; 6] = [0, 900, 500, 330, 320, 100];
+
+/// When to stop searching. The search ends as soon as any of the limits is reached, and
+/// without any limit it goes on to the deepest depth it supports.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// how many half moves deep to search
+    pub depth: Option<u32>,
+    /// how many positions to look at
+    pub nodes: Option<u64>,
+    /// how long to think
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    /// Searches to a fixed depth in half moves.
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// Searches until about this many positions have been looked at.
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// Searches for about this long.
+    pub fn time(time: Duration) -> SearchLimits {
+        SearchLimits {
+            time: Some(time),
+            ..SearchLimits::default()
+        }
+    }
+}
+
+/// How good a position is for the side to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Score {
+    /// an advantage in hundredths of a pawn
+    Centipawns(i32),
+    /// checkmate in this many moves, negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    fn from_internal(score: i32) -> Score {
+        if score >= MATE - MAX_PLY as i32 {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score <= -(MATE - MAX_PLY as i32) {
+            Score::Mate(-(MATE + score) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Centipawns(score) => write!(f, "{:+.2}", *score as f64 / 100.0),
+            Score::Mate(moves) if *moves < 0 => write!(f, "-M{}", -moves),
+            Score::Mate(moves) => write!(f, "M{}", moves),
+        }
+    }
+}
+
+/// What the search found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// `None` when the game is already over
+    pub best_move: Option<Move>,
+    pub score: Score,
+    /// the moves both sides are expected to play, starting with `best_move`
+    pub pv: Vec<Move>,
+    /// the deepest search that was finished
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Bound {
+    Exact,
+    /// the score is at least this, the search stopped at a move that was too good
+    Lower,
+    /// the score is at most this, no move reached alpha
+    Upper,
+}
+
+/// A position that was searched before, stored by its hash.
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    hash: u64,
+    depth: i32,
+    score: i32,
+    bound: Bound,
+    best_move: Option<Move>,
+}
+
+/// Searches for the best move. The engine keeps its transposition table and move ordering
+/// statistics between searches, which helps when it plays a whole game.
+pub struct Engine {
+    table: Vec<Option<Entry>>,
+    /// two quiet moves per ply that caused a cutoff
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// how often a quiet move caused a cutoff, by colour, from and to
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    /// the principal variation found at each ply
+    pv: Vec<Vec<Move>>,
+    time: Arc<dyn TimeSource>,
+    weights: Weights,
+    /// set from outside to end a search early
+    stop: Arc<AtomicBool>,
+
+    limits: SearchLimits,
+    started: Duration,
+    nodes: u64,
+    stopped: bool,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    /// Makes an engine with a 16 MB transposition table that times itself with the computer's clock.
+    pub fn new() -> Engine {
+        Engine::with_time_source(MonotonicTime::new())
+    }
+
+    /// Same as `new`, but measures time limits with the given source.
+    pub fn with_time_source(time: impl TimeSource + 'static) -> Engine {
+        let mut engine = Engine {
+            table: vec![],
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            pv: vec![vec![]; MAX_PLY + 1],
+            time: Arc::new(time),
+            weights: Weights::default(),
+            stop: Arc::new(AtomicBool::new(false)),
+            limits: SearchLimits::default(),
+            started: Duration::ZERO,
+            nodes: 0,
+            stopped: false,
+        };
+        engine.set_hash_size(16);
+        engine
+    }
+
+    /// Resizes the transposition table to at most this many megabytes, forgetting what it held.
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        let entries = (megabytes * 1024 * 1024 / std::mem::size_of::<Option<Entry>>()).max(1);
+        // a power of two, so the hash can be masked instead of divided
+        let entries = 1 << (usize::BITS - 1 - entries.leading_zeros());
+        self.table = vec![None; entries];
+    }
+
+    /// Evaluates positions with the given weights instead of the default ones.
+    pub fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+        // scores stored with the old weights would be misleading
+        self.clear();
+    }
+
+    /// Lets another thread end searches early: a search stops as soon as `stop` is true, and
+    /// returns the best move found so far. The engine never resets the flag itself.
+    pub fn set_stop_signal(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    /// Forgets everything learned in earlier searches, e.g. before a new game.
+    pub fn clear(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry = None);
+        self.killers = [[None; 2]; MAX_PLY];
+        *self.history = [[[0; 64]; 64]; 2];
+    }
+
+    /// Searches the position for the side to move until one of the limits is reached, and
+    /// returns the best move found in the deepest search that was finished.
+    pub fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        self.search_with_progress(game, limits, |_| ())
+    }
+
+    /// Same as `search`, but calls `progress` with the result of every depth as soon as it's
+    /// finished, e.g. to show what the engine is thinking.
+    pub fn search_with_progress(
+        &mut self,
+        game: &Game,
+        limits: SearchLimits,
+        mut progress: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let mut game = game.clone();
+        self.limits = limits;
+        self.started = self.time.now();
+        self.nodes = 0;
+        self.stopped = false;
+        self.killers = [[None; 2]; MAX_PLY];
+
+        let mut result = SearchResult {
+            best_move: None,
+            score: Score::Centipawns(0),
+            pv: vec![],
+            depth: 0,
+            nodes: 0,
+        };
+        if game.is_game_over() {
+            result.score = match game.get_game_state() {
+                GameState::CheckMate => Score::Mate(0),
+                _ => Score::Centipawns(0),
+            };
+            return result;
+        }
+
+        let max_depth = limits.depth.map_or(MAX_PLY as u32 - 1, |depth| {
+            depth.clamp(1, MAX_PLY as u32 - 1)
+        });
+        for depth in 1..=max_depth {
+            let score = self.negamax(&mut game, depth as i32, -INFINITY, INFINITY, 0);
+
+            // an unfinished search is only better than nothing
+            if self.stopped && result.best_move.is_some() {
+                break;
+            }
+            if let Some(&best_move) = self.pv[0].first() {
+                result.best_move = Some(best_move);
+                result.pv = self.pv[0].clone();
+                result.score = Score::from_internal(score);
+                result.depth = depth;
+                result.nodes = self.nodes;
+                if !self.stopped {
+                    progress(&result);
+                }
+            }
+            if self.stopped
+                || matches!(result.score, Score::Mate(moves) if moves.abs() * 2 <= depth as i32)
+            {
+                break;
+            }
+        }
+
+        // stopped before the first move was even searched
+        if result.best_move.is_none() {
+            result.best_move = game.legal_moves().first().copied();
+            result.pv = result.best_move.into_iter().collect();
+        }
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn negamax(
+        &mut self,
+        game: &mut Game,
+        mut depth: i32,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        self.pv[ply].clear();
+        if ply > 0 {
+            if let Some(score) = Engine::terminal_score(game, ply) {
+                return score;
+            }
+        }
+
+        let in_check = game.get_game_state() == GameState::Check;
+        if in_check {
+            depth += 1;
+        }
+        if depth <= 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(game, alpha, beta, ply);
+        }
+
+        if self.count_node() {
+            return 0;
+        }
+
+        let hash = game.hash();
+        let index = (hash as usize) & (self.table.len() - 1);
+        let mut table_move = None;
+        if let Some(entry) = self.table[index].filter(|entry| entry.hash == hash) {
+            table_move = entry.best_move;
+            let score = from_table(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if ply > 0 && entry.depth >= depth && usable {
+                return score;
+            }
+        }
+
+        let mut moves = MoveList::new();
+        game.generate_moves(&mut moves);
+        let mut scores = [0; MAX_MOVES];
+        for (i, mv) in moves.iter().enumerate() {
+            scores[i] = self.order_score(game, *mv, table_move, ply);
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for i in 0..moves.len() {
+            let mv = pick_next(&mut moves, &mut scores, i);
+
+            game.apply_move(mv);
+            let score = -self.negamax(game, depth - 1, -beta, -alpha, ply + 1);
+            game.take_back();
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(mv, ply);
+            }
+            if alpha >= beta {
+                if !mv.capture && mv.promotion.is_none() {
+                    self.remember_cutoff(game.active_colour, mv, depth, ply);
+                }
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table[index] = Some(Entry {
+            hash,
+            depth,
+            score: to_table(best_score, ply),
+            bound,
+            best_move,
+        });
+        best_score
+    }
+
+    /// only looks at captures and promotions, until the position is quiet, so the evaluation
+    /// isn't taken in the middle of an exchange
+    fn quiescence(&mut self, game: &mut Game, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+        if self.count_node() {
+            return 0;
+        }
+
+        // nothing is kept for the plies past the limit, not even to get out of check
+        if ply >= MAX_PLY - 1 {
+            return self.weights.evaluate(game);
+        }
+
+        // out of check every move has to be looked at, otherwise standing still is an option
+        let in_check = game.get_game_state() == GameState::Check;
+        let mut best_score = -INFINITY;
+        if !in_check {
+            best_score = self.weights.evaluate(game);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+        }
+
+        let mut moves = MoveList::new();
+        game.generate_moves(&mut moves);
+        let mut scores = [0; MAX_MOVES];
+        for (i, mv) in moves.iter().enumerate() {
+            scores[i] = self.order_score(game, *mv, None, ply);
+        }
+
+        for i in 0..moves.len() {
+            let mv = pick_next(&mut moves, &mut scores, i);
+            if !in_check && !mv.capture && mv.promotion.is_none() {
+                continue;
+            }
+
+            game.apply_move(mv);
+            let score = match Engine::terminal_score(game, ply + 1) {
+                Some(score) => -score,
+                None => -self.quiescence(game, -beta, -alpha, ply + 1),
+            };
+            game.take_back();
+            if self.stopped {
+                return 0;
+            }
+
+            best_score = best_score.max(score);
+            if score > alpha {
+                alpha = score;
+                self.update_pv(mv, ply);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    /// the score of a position where the game is over or a position repeats, for the side to move
+    fn terminal_score(game: &Game, ply: usize) -> Option<i32> {
+        match game.get_game_state() {
+            GameState::CheckMate => Some(-(MATE - ply as i32)),
+            GameState::InProgress | GameState::Check => {
+                // repeating a position once is as good as a draw, since it can be repeated again
+                if game.halfmove_clock >= 4 && game.get_repetition_count() > 1 {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            _ => Some(0),
+        }
+    }
+
+    /// counts a node and returns true once the search has to stop
+    fn count_node(&mut self) -> bool {
+        self.nodes += 1;
+        if self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        if let Some(nodes) = self.limits.nodes {
+            if self.nodes >= nodes {
+                self.stopped = true;
+            }
+        }
+        if let Some(time) = self.limits.time {
+            if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+                && self.time.now() - self.started >= time
+            {
+                self.stopped = true;
+            }
+        }
+        self.stopped
+    }
+
+    /// how promising a move looks, the higher the earlier it is searched
+    fn order_score(&self, game: &Game, mv: Move, table_move: Option<Move>, ply: usize) -> i32 {
+        if Some(mv) == table_move {
+            return 1_000_000;
+        }
+
+        let (from, to) = (mv.from.index(), mv.to.index());
+        if mv.capture || mv.promotion.is_some() {
+            // most valuable victim, least valuable attacker
+            let victim = match game.board[to] {
+                Some(piece) => piece_value(piece),
+                None if mv.en_passant => PIECE_VALUES[5],
+                None => 0,
+            };
+            let attacker = game.board[from].map_or(0, piece_value);
+            let promotion = mv.promotion.map_or(0, piece_value);
+            return 100_000 + victim * 10 - attacker / 10 + promotion;
+        }
+
+        if self.killers[ply][0] == Some(mv) {
+            90_000
+        } else if self.killers[ply][1] == Some(mv) {
+            80_000
+        } else {
+            self.history[colour_index(game.active_colour)][from][to]
+        }
+    }
+
+    /// a quiet move refuted the opponent's last move, so it's tried early in similar positions
+    fn remember_cutoff(&mut self, colour: Colour, mv: Move, depth: i32, ply: usize) {
+        if self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
+
+        let history = &mut self.history[colour_index(colour)][mv.from.index()][mv.to.index()];
+        // stays well below the killer moves
+        *history = (*history + depth * depth).min(50_000);
+    }
+
+    fn update_pv(&mut self, mv: Move, ply: usize) {
+        let (current, deeper) = self.pv.split_at_mut(ply + 1);
+        current[ply].clear();
+        current[ply].push(mv);
+        current[ply].extend_from_slice(&deeper[0]);
+    }
+}
+
+/// How long to think with this much time left on the clock, for the protocol frontends.
+pub(crate) fn time_for_move(
+    remaining: Duration,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+) -> Duration {
+    let moves = moves_to_go.unwrap_or(MOVES_TO_GO).max(1);
+    // never more than half of what's left, whatever the increment
+    (remaining / moves + increment / 2).min(remaining / 2)
+}
+
+/// moves the most promising of the remaining moves to `index` and returns it
+fn pick_next(moves: &mut MoveList, scores: &mut [i32; MAX_MOVES], index: usize) -> Move {
+    let mut best = index;
+    for i in index + 1..moves.len() {
+        if scores[i] > scores[best] {
+            best = i;
+        }
+    }
+    moves.swap(index, best);
+    scores.swap(index, best);
+    moves[index]
+}
+
+fn piece_value(piece: Piece) -> i32 {
+    PIECE_VALUES[piece_index(piece).1]
+}
+
+/// mate scores are stored as the distance from the stored position rather than from the root
+fn to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -(MATE - MAX_PLY as i32) {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -(MATE - MAX_PLY as i32) {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{time_for_move, Engine, Score, SearchLimits, INFINITY, MAX_PLY};
+    use crate::{Game, ManualTime, Square};
+
+    fn search(fen: &str, limits: SearchLimits) -> (Game, super::SearchResult) {
+        let game = Game::from_fen(fen).unwrap();
+        let result = Engine::new().search(&game, limits);
+        (game, result)
+    }
+
+    #[test]
+    fn checks_at_the_ply_limit() {
+        // white is in check and Qxe2+ checks back, so every ply down here is in check
+        let mut game = Game::from_fen("4k3/8/8/8/6Q1/8/4r3/4K3 w - - 0 1").unwrap();
+        let mut engine = Engine::new();
+        for ply in MAX_PLY - 3..MAX_PLY {
+            let score = engine.quiescence(&mut game, -INFINITY, INFINITY, ply);
+            assert!(score.abs() < INFINITY);
+        }
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let (game, result) = search("6k1/5ppp/8/8/8/8/8/3RK3 w - - 0 1", SearchLimits::depth(3));
+        assert_eq!(game.san(result.best_move.unwrap()).unwrap(), "Rd8#");
+        assert_eq!(result.score, Score::Mate(1));
+        assert_eq!(result.score.to_string(), "M1");
+    }
+
+    #[test]
+    fn mate_in_two() {
+        // 1. Re8+ Rxe8 2. Qxe8#
+        let (game, result) = search(
+            "3r2k1/5ppp/8/8/8/8/4RPPP/4Q1K1 w - - 0 1",
+            SearchLimits::depth(4),
+        );
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(game.san(result.best_move.unwrap()).unwrap(), "Re8+");
+        assert_eq!(result.pv.len(), 3);
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let (game, result) = search(
+            "rnb1kbnr/pppp1ppp/8/4p1q1/4P3/3P4/PPP2PPP/RNBQKBNR w KQkq - 0 1",
+            SearchLimits::depth(3),
+        );
+        assert_eq!(game.san(result.best_move.unwrap()).unwrap(), "Bxg5");
+        assert!(matches!(result.score, Score::Centipawns(score) if score > 500));
+    }
+
+    #[test]
+    fn avoids_losing_material() {
+        // the knight is attacked by a pawn and has to move
+        let (_, result) = search(
+            "rnbqkbnr/pppp1ppp/8/8/4p3/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1",
+            SearchLimits::depth(3),
+        );
+        assert_eq!(result.best_move.unwrap().from, Square::parse("f3").unwrap());
+    }
+
+    #[test]
+    fn principal_variation_is_legal() {
+        let (mut game, result) = search(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            SearchLimits::depth(4),
+        );
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+        for mv in result.pv {
+            assert!(game.play(mv).is_ok(), "{}", mv);
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let (_, result) = search(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            SearchLimits::nodes(2000),
+        );
+        assert!(result.nodes <= 2000);
+        assert!(result.best_move.is_some());
+
+        // time that never moves only stops the search through the other limits
+        let game = Game::new();
+        let mut engine = Engine::with_time_source(ManualTime::new());
+        let result = engine.search(
+            &game,
+            SearchLimits {
+                depth: Some(2),
+                time: Some(Duration::from_secs(1)),
+                ..SearchLimits::default()
+            },
+        );
+        assert_eq!(result.depth, 2);
+
+        // no time at all still gives a move
+        let result = Engine::new().search(&game, SearchLimits::time(Duration::ZERO));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn stop_signal_and_progress() {
+        let game = Game::new();
+        let mut engine = Engine::new();
+        let mut depths = vec![];
+        let result = engine.search_with_progress(&game, SearchLimits::depth(3), |result| {
+            depths.push(result.depth)
+        });
+        assert_eq!(depths, [1, 2, 3]);
+        assert_eq!(result.depth, 3);
+
+        // already stopped, so only a move is picked
+        let stop = Arc::new(AtomicBool::new(true));
+        engine.set_stop_signal(stop.clone());
+        let result = engine.search(&game, SearchLimits::default());
+        assert!(result.best_move.is_some());
+        assert_eq!(result.depth, 0);
+    }
+
+    #[test]
+    fn finished_games() {
+        let (_, result) = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", SearchLimits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, Score::Centipawns(0));
+
+        let (_, result) = search("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1", SearchLimits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, Score::Mate(0));
+    }
+
+    #[test]
+    fn time_management() {
+        let second = Duration::from_secs(1);
+        assert_eq!(time_for_move(30 * second, Duration::ZERO, None), second);
+        assert_eq!(
+            time_for_move(10 * second, Duration::ZERO, Some(5)),
+            2 * second
+        );
+        assert_eq!(time_for_move(second, 4 * second, None), second / 2);
+    }
+}
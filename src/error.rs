@@ -0,0 +1,67 @@
+//! Errors returned by the `Result` based parts of the `Game` API.
+
+use std::fmt;
+
+use crate::{Move, Square};
+
+/// Describes why a move or query was refused.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChessError {
+    /// a square that isn't written like "e4"
+    InvalidNotation(String),
+    /// a move that can't be read, e.g. SAN that matches no legal move
+    InvalidMove(String),
+    /// a promotion choice other than "q", "r", "n" or "b"
+    InvalidPromotionPiece(String),
+    /// there is no piece on the square
+    EmptySquare(Square),
+    /// the piece on the square belongs to the other side
+    WrongColour(Square),
+    /// the piece can't make this move
+    IllegalMove(Move),
+    /// a pawn reaching the last rank needs a piece to promote to
+    PromotionRequired(Move),
+    /// a promotion choice was given for a move that isn't a promotion,
+    /// or the piece isn't a queen, rook, knight or bishop of the side to move
+    InvalidPromotion(Move),
+    /// only pawns of the side to move can be promoted
+    NotAPawn(Square),
+    /// only pawns standing on the last rank can be promoted
+    NotOnLastRank(Square),
+    /// the game has ended and doesn't accept any more moves
+    GameOver,
+    /// a draw was claimed in a position where the rules don't allow it
+    NoDrawToClaim,
+    /// the opponent hasn't offered a draw that could be accepted or declined
+    NoDrawOffer,
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChessError::InvalidNotation(notation) => {
+                write!(f, "'{}' is not a square", notation)
+            }
+            ChessError::InvalidMove(text) => write!(f, "'{}' is not a legal move", text),
+            ChessError::InvalidPromotionPiece(piece) => {
+                write!(f, "can only promote to q, r, n or b, not '{}'", piece)
+            }
+            ChessError::EmptySquare(square) => write!(f, "no piece on {}", square),
+            ChessError::WrongColour(square) => write!(f, "the piece on {} is not yours", square),
+            ChessError::IllegalMove(mv) => write!(f, "{} is not a legal move", mv),
+            ChessError::PromotionRequired(mv) => {
+                write!(f, "{} needs a piece to promote to", mv)
+            }
+            ChessError::InvalidPromotion(mv) => write!(f, "{} is not a valid promotion", mv),
+            ChessError::NotAPawn(square) => write!(f, "the piece on {} is not your pawn", square),
+            ChessError::NotOnLastRank(square) => {
+                write!(f, "the pawn on {} hasn't reached the last rank", square)
+            }
+            ChessError::GameOver => write!(f, "the game is over"),
+            ChessError::NoDrawToClaim => write!(f, "there is no draw to claim"),
+            ChessError::NoDrawOffer => write!(f, "no draw has been offered"),
+        }
+    }
+}
+
+impl std::error::Error for ChessError {}
@@ -0,0 +1,657 @@
+//! Static evaluation: how good a position looks without searching any moves.
+//!
+//! The score is made up of material, piece-square tables, mobility, king safety, pawn structure
+//! and the bishop pair. Most terms have a middlegame and an endgame weight, and the two scores are
+//! blended by how much material is left on the board (a tapered evaluation).
+//!
+//! All weights live in `Weights`, which can be written to and read from a text file with one
+//! weight per line, so they can be tuned without recompiling:
+//!
+//! ```text
+//! # material in the order king, queen, rook, bishop, knight, pawn
+//! material_mg = 0 900 500 330 320 100
+//! doubled_pawn_eg = -20
+//! ```
+//!
+//! Weights left out of a file keep their default value.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::bitboard::{self, Positions};
+use crate::{Colour, Game, Piece};
+
+/// The names of the kinds of piece, in the order weights are given for them.
+const KINDS: [&str; 6] = ["king", "queen", "rook", "bishop", "knight", "pawn"];
+const KING: usize = 0;
+const BISHOP: usize = 3;
+const PAWN: usize = 5;
+
+const FILE: u64 = 0x0101_0101_0101_0101;
+
+/// The weights of every term of the evaluation, in centipawns. Terms ending in `_mg` count in
+/// the middlegame and terms ending in `_eg` in the endgame. Negative weights are penalties.
+/// Arrays by kind of piece are in the order king, queen, rook, bishop, knight, pawn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Weights {
+    pub material_mg: [i32; 6],
+    pub material_eg: [i32; 6],
+    /// bonuses for standing on a square, by kind of piece, from white's point of view with
+    /// a8 first and h1 last, mirrored for black
+    pub psqt_mg: [[i32; 64]; 6],
+    pub psqt_eg: [[i32; 64]; 6],
+    /// for every square a piece can move to, kings don't count
+    pub mobility_mg: [i32; 6],
+    pub mobility_eg: [i32; 6],
+    /// how much each piece counts towards the middlegame, the phase is the sum of these
+    /// and goes from the full starting material down to zero in a pawn ending
+    pub phase: [i32; 6],
+    /// for every own pawn right in front of the king (middlegame only)
+    pub pawn_shield: i32,
+    /// for every square around the king, and the king's own square, the opponent attacks
+    /// (middlegame only)
+    pub king_attack: i32,
+    /// for every pawn behind another of the same colour on its file
+    pub doubled_pawn_mg: i32,
+    pub doubled_pawn_eg: i32,
+    /// for every pawn without pawns of the same colour on the files next to it
+    pub isolated_pawn_mg: i32,
+    pub isolated_pawn_eg: i32,
+    /// for a pawn no enemy pawn can stop, by the rank it has reached (1 to 8 from its own side)
+    pub passed_pawn_mg: [i32; 8],
+    pub passed_pawn_eg: [i32; 8],
+    pub bishop_pair_mg: i32,
+    pub bishop_pair_eg: i32,
+}
+
+#[rustfmt::skip]
+static DEFAULT_WEIGHTS: Weights = Weights {
+    material_mg: [0, 900, 500, 330, 320, 100],
+    material_eg: [0, 950, 520, 330, 300, 120],
+    psqt_mg: [
+        // king, stays behind its pawns
+        [
+            -30, -40, -40, -50, -50, -40, -40, -30,
+            -30, -40, -40, -50, -50, -40, -40, -30,
+            -30, -40, -40, -50, -50, -40, -40, -30,
+            -30, -40, -40, -50, -50, -40, -40, -30,
+            -20, -30, -30, -40, -40, -30, -30, -20,
+            -10, -20, -20, -20, -20, -20, -20, -10,
+             20,  20,   0,   0,   0,   0,  20,  20,
+             20,  30,  10,   0,   0,  10,  30,  20,
+        ],
+        // queen
+        [
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,   5,   5,   5,   0, -10,
+             -5,   0,   5,   5,   5,   5,   0,  -5,
+              0,   0,   5,   5,   5,   5,   0,  -5,
+            -10,   5,   5,   5,   5,   5,   0, -10,
+            -10,   0,   5,   0,   0,   0,   0, -10,
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+        ],
+        // rook
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+              5,  10,  10,  10,  10,  10,  10,   5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+              0,   0,   0,   5,   5,   0,   0,   0,
+        ],
+        // bishop
+        [
+            -20, -10, -10, -10, -10, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,  10,  10,   5,   0, -10,
+            -10,   5,   5,  10,  10,   5,   5, -10,
+            -10,   0,  10,  10,  10,  10,   0, -10,
+            -10,  10,  10,  10,  10,  10,  10, -10,
+            -10,   5,   0,   0,   0,   0,   5, -10,
+            -20, -10, -10, -10, -10, -10, -10, -20,
+        ],
+        // knight
+        [
+            -50, -40, -30, -30, -30, -30, -40, -50,
+            -40, -20,   0,   0,   0,   0, -20, -40,
+            -30,   0,  10,  15,  15,  10,   0, -30,
+            -30,   5,  15,  20,  20,  15,   5, -30,
+            -30,   0,  15,  20,  20,  15,   0, -30,
+            -30,   5,  10,  15,  15,  10,   5, -30,
+            -40, -20,   0,   5,   5,   0, -20, -40,
+            -50, -40, -30, -30, -30, -30, -40, -50,
+        ],
+        // pawn
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+             50,  50,  50,  50,  50,  50,  50,  50,
+             10,  10,  20,  30,  30,  20,  10,  10,
+              5,   5,  10,  25,  25,  10,   5,   5,
+              0,   0,   0,  20,  20,   0,   0,   0,
+              5,  -5, -10,   0,   0, -10,  -5,   5,
+              5,  10,  10, -20, -20,  10,  10,   5,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+    ],
+    psqt_eg: [
+        // king, comes to the centre once the queens are gone
+        [
+            -50, -40, -30, -20, -20, -30, -40, -50,
+            -30, -20, -10,   0,   0, -10, -20, -30,
+            -30, -10,  20,  30,  30,  20, -10, -30,
+            -30, -10,  30,  40,  40,  30, -10, -30,
+            -30, -10,  30,  40,  40,  30, -10, -30,
+            -30, -10,  20,  30,  30,  20, -10, -30,
+            -30, -30,   0,   0,   0,   0, -30, -30,
+            -50, -30, -30, -30, -30, -30, -30, -50,
+        ],
+        // queen
+        [
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,   5,   5,   5,   0, -10,
+             -5,   0,   5,   5,   5,   5,   0,  -5,
+             -5,   0,   5,   5,   5,   5,   0,  -5,
+            -10,   0,   5,   5,   5,   5,   0, -10,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+        ],
+        // rook
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+              5,  10,  10,  10,  10,  10,  10,   5,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+        // bishop
+        [
+            -20, -10, -10, -10, -10, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,  10,  10,   5,   0, -10,
+            -10,   5,   5,  10,  10,   5,   5, -10,
+            -10,   0,  10,  10,  10,  10,   0, -10,
+            -10,  10,  10,  10,  10,  10,  10, -10,
+            -10,   5,   0,   0,   0,   0,   5, -10,
+            -20, -10, -10, -10, -10, -10, -10, -20,
+        ],
+        // knight
+        [
+            -50, -40, -30, -30, -30, -30, -40, -50,
+            -40, -20,   0,   0,   0,   0, -20, -40,
+            -30,   0,  10,  15,  15,  10,   0, -30,
+            -30,   5,  15,  20,  20,  15,   5, -30,
+            -30,   0,  15,  20,  20,  15,   0, -30,
+            -30,   5,  10,  15,  15,  10,   5, -30,
+            -40, -20,   0,   5,   5,   0, -20, -40,
+            -50, -40, -30, -30, -30, -30, -40, -50,
+        ],
+        // pawn, the further the better
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+             80,  80,  80,  80,  80,  80,  80,  80,
+             50,  50,  50,  50,  50,  50,  50,  50,
+             30,  30,  30,  30,  30,  30,  30,  30,
+             20,  20,  20,  20,  20,  20,  20,  20,
+             10,  10,  10,  10,  10,  10,  10,  10,
+             10,  10,  10,  10,  10,  10,  10,  10,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+    ],
+    mobility_mg: [0, 1, 2, 3, 4, 0],
+    mobility_eg: [0, 2, 4, 3, 3, 0],
+    phase: [0, 4, 2, 1, 1, 0],
+    pawn_shield: 10,
+    king_attack: -8,
+    doubled_pawn_mg: -10,
+    doubled_pawn_eg: -20,
+    isolated_pawn_mg: -10,
+    isolated_pawn_eg: -15,
+    passed_pawn_mg: [0, 5, 10, 15, 25, 40, 60, 0],
+    passed_pawn_eg: [0, 10, 20, 35, 60, 90, 130, 0],
+    bishop_pair_mg: 30,
+    bishop_pair_eg: 50,
+};
+
+/// Scores the position with the default weights, in centipawns for the side to move.
+pub fn evaluate(game: &Game) -> i32 {
+    DEFAULT_WEIGHTS.evaluate(game)
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        DEFAULT_WEIGHTS.clone()
+    }
+}
+
+/// Describes what is wrong with a weights file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WeightsError {
+    /// a line that isn't written as `name = values`
+    InvalidLine(String),
+    UnknownWeight(String),
+    InvalidNumber(String),
+    /// a weight with the wrong number of values
+    WrongCount {
+        weight: String,
+        expected: usize,
+        found: usize,
+    },
+    /// the file couldn't be read or written
+    Io(String),
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::InvalidLine(line) => {
+                write!(f, "expected 'name = values', found '{}'", line)
+            }
+            WeightsError::UnknownWeight(name) => write!(f, "unknown weight '{}'", name),
+            WeightsError::InvalidNumber(number) => write!(f, "invalid number '{}'", number),
+            WeightsError::WrongCount {
+                weight,
+                expected,
+                found,
+            } => write!(f, "'{}' needs {} values, found {}", weight, expected, found),
+            WeightsError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for WeightsError {}
+
+impl Weights {
+    /// Reads weights written as `name = values`, one per line. Empty lines and lines starting
+    /// with `#` are skipped, and weights that aren't mentioned keep their default value.
+    pub fn parse(text: &str) -> Result<Weights, WeightsError> {
+        let mut weights = Weights::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, values) = line
+                .split_once('=')
+                .ok_or_else(|| WeightsError::InvalidLine(line.to_string()))?;
+            let name = name.trim();
+
+            let values = values
+                .split_whitespace()
+                .map(|value| {
+                    value
+                        .parse::<i32>()
+                        .map_err(|_| WeightsError::InvalidNumber(value.to_string()))
+                })
+                .collect::<Result<Vec<i32>, WeightsError>>()?;
+
+            let mut fields = weights.fields_mut();
+            let field = fields
+                .iter_mut()
+                .find(|(field, _)| *field == name)
+                .map(|(_, field)| field)
+                .ok_or_else(|| WeightsError::UnknownWeight(name.to_string()))?;
+            if field.len() != values.len() {
+                return Err(WeightsError::WrongCount {
+                    weight: name.to_string(),
+                    expected: field.len(),
+                    found: values.len(),
+                });
+            }
+            field.copy_from_slice(&values);
+        }
+        Ok(weights)
+    }
+
+    /// Reads weights from a file in the format of `parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Weights, WeightsError> {
+        let text = fs::read_to_string(path).map_err(|error| WeightsError::Io(error.to_string()))?;
+        Weights::parse(&text)
+    }
+
+    /// Writes every weight to a file that `load` can read back.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WeightsError> {
+        fs::write(path, self.to_string()).map_err(|error| WeightsError::Io(error.to_string()))
+    }
+
+    /// every weight by its name in the file format
+    fn fields_mut(&mut self) -> Vec<(String, &mut [i32])> {
+        let mut fields: Vec<(String, &mut [i32])> = vec![
+            ("material_mg".to_string(), &mut self.material_mg),
+            ("material_eg".to_string(), &mut self.material_eg),
+        ];
+        for (kind, table) in KINDS.iter().zip(self.psqt_mg.iter_mut()) {
+            fields.push((format!("{}_psqt_mg", kind), table));
+        }
+        for (kind, table) in KINDS.iter().zip(self.psqt_eg.iter_mut()) {
+            fields.push((format!("{}_psqt_eg", kind), table));
+        }
+        fields.extend([
+            ("mobility_mg".to_string(), &mut self.mobility_mg[..]),
+            ("mobility_eg".to_string(), &mut self.mobility_eg),
+            ("phase".to_string(), &mut self.phase),
+            (
+                "pawn_shield".to_string(),
+                std::slice::from_mut(&mut self.pawn_shield),
+            ),
+            (
+                "king_attack".to_string(),
+                std::slice::from_mut(&mut self.king_attack),
+            ),
+            (
+                "doubled_pawn_mg".to_string(),
+                std::slice::from_mut(&mut self.doubled_pawn_mg),
+            ),
+            (
+                "doubled_pawn_eg".to_string(),
+                std::slice::from_mut(&mut self.doubled_pawn_eg),
+            ),
+            (
+                "isolated_pawn_mg".to_string(),
+                std::slice::from_mut(&mut self.isolated_pawn_mg),
+            ),
+            (
+                "isolated_pawn_eg".to_string(),
+                std::slice::from_mut(&mut self.isolated_pawn_eg),
+            ),
+            ("passed_pawn_mg".to_string(), &mut self.passed_pawn_mg),
+            ("passed_pawn_eg".to_string(), &mut self.passed_pawn_eg),
+            (
+                "bishop_pair_mg".to_string(),
+                std::slice::from_mut(&mut self.bishop_pair_mg),
+            ),
+            (
+                "bishop_pair_eg".to_string(),
+                std::slice::from_mut(&mut self.bishop_pair_eg),
+            ),
+        ]);
+        fields
+    }
+
+    /// Scores the position with these weights, in centipawns for the side to move.
+    pub fn evaluate(&self, game: &Game) -> i32 {
+        let (white_mg, white_eg, white_phase) = self.evaluate_side(game, Colour::White);
+        let (black_mg, black_eg, black_phase) = self.evaluate_side(game, Colour::Black);
+        let (mg, eg) = (white_mg - black_mg, white_eg - black_eg);
+
+        // all the pieces of the starting position, except kings and pawns
+        let full_phase = 2 * (self.phase[1] + 2 * (self.phase[2] + self.phase[3] + self.phase[4]));
+        let score = if full_phase <= 0 {
+            eg
+        } else {
+            let phase = (white_phase + black_phase).clamp(0, full_phase);
+            (mg * phase + eg * (full_phase - phase)) / full_phase
+        };
+
+        match game.active_colour {
+            Colour::White => score,
+            Colour::Black => -score,
+        }
+    }
+
+    /// the middlegame and endgame score of one side, and how much it adds to the phase
+    fn evaluate_side(&self, game: &Game, colour: Colour) -> (i32, i32, i32) {
+        let bitboards = &game.bitboards;
+        let (mut mg, mut eg, mut phase) = (0, 0, 0);
+
+        let pieces = [
+            Piece::King(colour),
+            Piece::Queen(colour),
+            Piece::Rook(colour),
+            Piece::Bishop(colour),
+            Piece::Knight(colour),
+            Piece::Pawn(colour),
+        ];
+        for (kind, piece) in pieces.iter().enumerate() {
+            for position in Positions(bitboards.pieces(*piece)) {
+                let square = table_square(position, colour);
+                mg += self.material_mg[kind] + self.psqt_mg[kind][square];
+                eg += self.material_eg[kind] + self.psqt_eg[kind][square];
+                phase += self.phase[kind];
+
+                if kind != KING && kind != PAWN {
+                    let moves = game
+                        .pseudo_targets(bitboards, position, colour)
+                        .count_ones() as i32;
+                    mg += self.mobility_mg[kind] * moves;
+                    eg += self.mobility_eg[kind] * moves;
+                }
+            }
+        }
+
+        if bitboards.pieces(pieces[BISHOP]).count_ones() >= 2 {
+            mg += self.bishop_pair_mg;
+            eg += self.bishop_pair_eg;
+        }
+
+        let (pawns_mg, pawns_eg) = self.pawn_structure(game, colour);
+        mg += pawns_mg + self.king_safety(game, colour);
+        eg += pawns_eg;
+        (mg, eg, phase)
+    }
+
+    fn pawn_structure(&self, game: &Game, colour: Colour) -> (i32, i32) {
+        let own = game.bitboards.pieces(Piece::Pawn(colour));
+        let enemy = game.bitboards.pieces(Piece::Pawn(other_colour(colour)));
+        let (mut mg, mut eg) = (0, 0);
+
+        for file in 0..8 {
+            let doubled = (own & FILE << file).count_ones().saturating_sub(1) as i32;
+            mg += self.doubled_pawn_mg * doubled;
+            eg += self.doubled_pawn_eg * doubled;
+        }
+
+        for position in Positions(own) {
+            let (file, rank) = (position % 8, position / 8);
+            let neighbours = neighbour_files(file);
+            if own & neighbours == 0 {
+                mg += self.isolated_pawn_mg;
+                eg += self.isolated_pawn_eg;
+            }
+
+            let ahead = match colour {
+                Colour::White => !0u64 << (rank * 8) << 8,
+                Colour::Black => (1u64 << (rank * 8)) - 1,
+            };
+            if enemy & (FILE << file | neighbours) & ahead == 0 {
+                let relative_rank = match colour {
+                    Colour::White => rank,
+                    Colour::Black => 7 - rank,
+                };
+                mg += self.passed_pawn_mg[relative_rank];
+                eg += self.passed_pawn_eg[relative_rank];
+            }
+        }
+        (mg, eg)
+    }
+
+    /// pawns sheltering the king and enemy attacks around it, which only matter in the middlegame
+    fn king_safety(&self, game: &Game, colour: Colour) -> i32 {
+        let king = game.bitboards.pieces(Piece::King(colour));
+        if king == 0 {
+            return 0;
+        }
+        let position = king.trailing_zeros() as usize;
+        let zone = bitboard::king_attacks(position) | king;
+
+        let ahead = match colour {
+            Colour::White => !0u64 << (position / 8 * 8) << 8,
+            Colour::Black => (1u64 << (position / 8 * 8)) - 1,
+        };
+        let shield =
+            (zone & ahead & game.bitboards.pieces(Piece::Pawn(colour))).count_ones() as i32;
+
+        let attacked = Positions(zone)
+            .filter(|&square| game.bitboards.attacked(square, other_colour(colour)))
+            .count() as i32;
+
+        self.pawn_shield * shield + self.king_attack * attacked
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // writing only reads the fields, but they are listed in one place for both directions
+        let mut weights = self.clone();
+        for (name, values) in weights.fields_mut() {
+            let values: Vec<String> = values.iter().map(i32::to_string).collect();
+            writeln!(f, "{} = {}", name, values.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// the index into a piece-square table, which is laid out from white's side with a8 first
+fn table_square(position: usize, colour: Colour) -> usize {
+    // the board is backwards, file 0 is the h file
+    let (file, rank) = (7 - position % 8, position / 8);
+    match colour {
+        Colour::White => (7 - rank) * 8 + file,
+        Colour::Black => rank * 8 + file,
+    }
+}
+
+/// every square on the files next to the given one
+fn neighbour_files(file: usize) -> u64 {
+    let mut files = 0;
+    if file > 0 {
+        files |= FILE << (file - 1);
+    }
+    if file < 7 {
+        files |= FILE << (file + 1);
+    }
+    files
+}
+
+fn other_colour(colour: Colour) -> Colour {
+    match colour {
+        Colour::White => Colour::Black,
+        Colour::Black => Colour::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, Weights, WeightsError};
+    use crate::Game;
+
+    fn score(fen: &str) -> i32 {
+        evaluate(&Game::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn symmetric() {
+        assert_eq!(evaluate(&Game::new()), 0);
+
+        // the same position with the colours swapped scores the same for the side to move
+        assert_eq!(
+            score("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"),
+            score("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1")
+        );
+        assert_eq!(
+            score("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+            -score("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1")
+        );
+    }
+
+    #[test]
+    fn material() {
+        // white is a queen up
+        let up = score("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert!(up > 800, "{}", up);
+        let down = score("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(down, -up);
+    }
+
+    #[test]
+    fn pawn_structure() {
+        // a passed pawn is worth more than a blocked one, and more the further it is
+        let passed = score("4k3/8/8/8/3P4/8/8/4K3 w - - 0 1");
+        let blocked = score("4k3/3p4/8/8/3P4/8/8/4K3 w - - 0 1");
+        let advanced = score("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1");
+        assert!(passed > blocked + 100, "{} {}", passed, blocked);
+        assert!(advanced > passed);
+
+        // doubled and isolated pawns are weaker than healthy ones
+        let healthy = score("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1");
+        let doubled = score("4k3/pp6/8/8/8/P7/P7/4K3 w - - 0 1");
+        let isolated = score("4k3/pp6/8/8/8/8/P1P5/4K3 w - - 0 1");
+        assert!(healthy > doubled);
+        assert!(healthy > isolated);
+    }
+
+    #[test]
+    fn tapered() {
+        // with only kings and pawns left the king belongs in the centre
+        let centre = score("4k3/pppp4/8/8/3K4/8/PPPP4/8 w - - 0 1");
+        let corner = score("4k3/pppp4/8/8/8/8/PPPP4/7K w - - 0 1");
+        assert!(centre > corner);
+
+        // with all the pieces still on, it's better off castled
+        let castled = score("rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w - - 0 1");
+        let centre = score("rnbq1rk1/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1R2 w - - 0 1");
+        assert!(castled > centre);
+    }
+
+    #[test]
+    fn weights_files() {
+        let weights = Weights::default();
+        assert_eq!(Weights::parse(&weights.to_string()), Ok(weights.clone()));
+
+        let changed = Weights::parse(
+            "# no bonus for mobility
+            mobility_mg = 0 0 0 0 0 0
+
+            pawn_shield = 25",
+        )
+        .unwrap();
+        assert_eq!(changed.mobility_mg, [0; 6]);
+        assert_eq!(changed.pawn_shield, 25);
+        assert_eq!(changed.material_mg, weights.material_mg);
+
+        let path = std::env::temp_dir().join("wingmyr-chess-weights.txt");
+        changed.save(&path).unwrap();
+        assert_eq!(Weights::load(&path), Ok(changed));
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            Weights::parse("pawn_shield 25"),
+            Err(WeightsError::InvalidLine("pawn_shield 25".to_string()))
+        );
+        assert_eq!(
+            Weights::parse("mobility = 1"),
+            Err(WeightsError::UnknownWeight("mobility".to_string()))
+        );
+        assert_eq!(
+            Weights::parse("phase = 0 4 2 x 1 0"),
+            Err(WeightsError::InvalidNumber("x".to_string()))
+        );
+        assert_eq!(
+            Weights::parse("material_eg = 1 2 3"),
+            Err(WeightsError::WrongCount {
+                weight: "material_eg".to_string(),
+                expected: 6,
+                found: 3,
+            })
+        );
+        assert!(matches!(
+            Weights::load("/nonexistent/weights.txt"),
+            Err(WeightsError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn weights_change_the_score() {
+        let game = Game::from_fen("4k3/8/8/8/3P4/8/8/4K3 w - - 0 1").unwrap();
+        let mut weights = Weights::default();
+        let before = weights.evaluate(&game);
+        weights.passed_pawn_eg[3] += 100;
+        assert_eq!(weights.evaluate(&game), before + 100);
+    }
+}
@@ -0,0 +1,547 @@
+//! Reading and writing positions in Forsyth-Edwards Notation (FEN).
+//!
+//! A FEN string has six space separated fields, e.g. the starting position:
+//! `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1`
+//! 1. piece placement from rank 8 down to rank 1, files a to h, white pieces in uppercase
+//! 2. side to move, `w` or `b`
+//! 3. castling rights, any of `KQkq` or `-`
+//! 4. en passant square or `-`
+//! 5. halfmove clock
+//! 6. fullmove number
+//!
+//! The two clocks may be left out, in which case they default to `0 1`.
+
+use std::fmt;
+
+use crate::{CastlingRights, Colour, Game, GameState, Piece, Square};
+
+/// Describes what is wrong with a FEN string.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FenError {
+    /// the string doesn't have four or six space separated fields
+    WrongFieldCount(usize),
+    /// the piece placement doesn't describe exactly eight ranks
+    WrongRankCount(usize),
+    /// a rank (1-8) doesn't add up to eight squares
+    WrongRankLength {
+        rank: usize,
+        length: usize,
+    },
+    /// a character in the piece placement that isn't a piece or a digit from 1 to 8
+    InvalidPiece(char),
+    /// each side needs exactly one king, this colour has the given number
+    InvalidKingCount {
+        colour: Colour,
+        count: usize,
+    },
+    /// pawns can't stand on the first or the last rank
+    PawnOnBackRank(Square),
+    /// the side that just moved has left its king in check
+    InactiveSideInCheck(Colour),
+    InvalidActiveColour(String),
+    InvalidCastling(String),
+    /// a castling right ('K', 'Q', 'k' or 'q') without the king and rook on their starting squares
+    CastlingMismatch(char),
+    InvalidEnPassant(String),
+    /// no pawn can have just moved past the en passant square
+    NoEnPassantPawn(Square),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 or 6 fields, found {}", count)
+            }
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+            FenError::WrongRankLength { rank, length } => {
+                write!(f, "rank {} has {} squares instead of 8", rank, length)
+            }
+            FenError::InvalidPiece(symbol) => write!(f, "'{}' is not a piece", symbol),
+            FenError::InvalidKingCount { colour, count } => {
+                write!(f, "{:?} has {} kings instead of 1", colour, count)
+            }
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on {}", square),
+            FenError::InactiveSideInCheck(colour) => {
+                write!(f, "{:?} is in check but it isn't their move", colour)
+            }
+            FenError::InvalidActiveColour(field) => {
+                write!(f, "side to move must be 'w' or 'b', found '{}'", field)
+            }
+            FenError::InvalidCastling(field) => write!(f, "invalid castling rights '{}'", field),
+            FenError::CastlingMismatch(symbol) => {
+                write!(f, "no king and rook for castling right '{}'", symbol)
+            }
+            FenError::InvalidEnPassant(field) => {
+                write!(f, "invalid en passant square '{}'", field)
+            }
+            FenError::NoEnPassantPawn(square) => {
+                write!(f, "no pawn has just moved past {}", square)
+            }
+            FenError::InvalidHalfmoveClock(field) => {
+                write!(f, "invalid halfmove clock '{}'", field)
+            }
+            FenError::InvalidFullmoveNumber(field) => {
+                write!(f, "invalid fullmove number '{}'", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Game {
+    /// Sets up a game from a FEN string.
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let board = parse_placement(fields[0])?;
+
+        let active_colour = match fields[1] {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            other => return Err(FenError::InvalidActiveColour(other.to_string())),
+        };
+
+        let (white_castling, black_castling) = parse_castling(fields[2])?;
+        check_castling(&board, white_castling, black_castling)?;
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => {
+                // the skipped square is on rank 3 after a white double step and on rank 6 after a black one
+                let expected_rank = match active_colour {
+                    Colour::White => 5,
+                    Colour::Black => 2,
+                };
+                match Square::parse(square).map(|square| square.index() as i32) {
+                    Some(position) if position / 8 == expected_rank => {
+                        check_en_passant(&board, active_colour, position as usize)?;
+                        Some(position)
+                    }
+                    _ => return Err(FenError::InvalidEnPassant(square.to_string())),
+                }
+            }
+        };
+
+        let (halfmove_clock, fullmove_number) = if fields.len() == 6 {
+            let halfmove_clock = fields[4]
+                .parse::<u32>()
+                .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+            let fullmove_number = match fields[5].parse::<u32>() {
+                Ok(number) if number > 0 => number,
+                _ => return Err(FenError::InvalidFullmoveNumber(fields[5].to_string())),
+            };
+            (halfmove_clock, fullmove_number)
+        } else {
+            (0, 1)
+        };
+
+        let mut game = Game::new();
+        game.set_board(board);
+        game.active_colour = active_colour;
+        game.white_castling = white_castling;
+        game.black_castling = black_castling;
+        game.en_passant = en_passant;
+        game.halfmove_clock = halfmove_clock;
+        game.fullmove_number = fullmove_number;
+        game.hash = game.compute_hash();
+
+        // the side that just moved can't have left its king attacked
+        let inactive_colour = match active_colour {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        };
+        if game.check_checker(inactive_colour) {
+            return Err(FenError::InactiveSideInCheck(inactive_colour));
+        }
+
+        game.start_fen = Some(game.to_fen());
+
+        game.state = GameState::InProgress;
+        game.update_state();
+
+        Ok(game)
+    }
+
+    /// Describes the current position as a FEN string.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            // board is backwards, a is the highest index of the rank
+            for file in (0..8).rev() {
+                match self.board[rank * 8 + file] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement += &empty.to_string();
+                            empty = 0;
+                        }
+                        placement.push(piece_symbol(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement += &empty.to_string();
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let active_colour = match self.active_colour {
+            Colour::White => "w",
+            Colour::Black => "b",
+        };
+
+        let mut castling = String::new();
+        for (allowed, symbol) in [
+            (self.white_castling.king_side, 'K'),
+            (self.white_castling.queen_side, 'Q'),
+            (self.black_castling.king_side, 'k'),
+            (self.black_castling.queen_side, 'q'),
+        ] {
+            if allowed {
+                castling.push(symbol);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = self.get_en_passant().unwrap_or_else(|| "-".to_string());
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            active_colour,
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
+
+/// reads the piece placement field into a board
+fn parse_placement(field: &str) -> Result<[Option<Piece>; 64], FenError> {
+    let mut board = [None; 64];
+
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    // FEN starts with rank 8
+    for (row, symbols) in ranks.iter().enumerate() {
+        let rank = 7 - row;
+        let mut file = 0; // counted from a
+
+        for symbol in symbols.chars() {
+            if let Some(skip) = symbol.to_digit(10) {
+                if !(1..=8).contains(&skip) {
+                    return Err(FenError::InvalidPiece(symbol));
+                }
+                file += skip as usize;
+                continue;
+            }
+
+            let piece = parse_piece(symbol).ok_or(FenError::InvalidPiece(symbol))?;
+            if file < 8 {
+                // board is backwards
+                board[rank * 8 + 7 - file] = Some(piece);
+            }
+            file += 1;
+        }
+
+        if file != 8 {
+            return Err(FenError::WrongRankLength {
+                rank: rank + 1,
+                length: file,
+            });
+        }
+    }
+
+    for colour in [Colour::White, Colour::Black] {
+        let count = board
+            .iter()
+            .filter(|piece| **piece == Some(Piece::King(colour)))
+            .count();
+        if count != 1 {
+            return Err(FenError::InvalidKingCount { colour, count });
+        }
+    }
+
+    for (index, piece) in board.iter().enumerate() {
+        let back_rank = !(8..56).contains(&index);
+        if back_rank && matches!(piece, Some(Piece::Pawn(_))) {
+            return Err(FenError::PawnOnBackRank(Square::from_index(index).unwrap()));
+        }
+    }
+
+    Ok(board)
+}
+
+/// reads the castling field into white's and black's castling rights
+fn parse_castling(field: &str) -> Result<(CastlingRights, CastlingRights), FenError> {
+    let mut white = CastlingRights {
+        king_side: false,
+        queen_side: false,
+    };
+    let mut black = white;
+
+    if field == "-" {
+        return Ok((white, black));
+    }
+
+    for symbol in field.chars() {
+        let right = match symbol {
+            'K' => &mut white.king_side,
+            'Q' => &mut white.queen_side,
+            'k' => &mut black.king_side,
+            'q' => &mut black.queen_side,
+            _ => return Err(FenError::InvalidCastling(field.to_string())),
+        };
+        // every right can only be given once
+        if *right {
+            return Err(FenError::InvalidCastling(field.to_string()));
+        }
+        *right = true;
+    }
+
+    Ok((white, black))
+}
+
+/// checks that every castling right has its king and rook on their starting squares
+fn check_castling(
+    board: &[Option<Piece>; 64],
+    white: CastlingRights,
+    black: CastlingRights,
+) -> Result<(), FenError> {
+    // board is backwards: e1 is 3, h1 is 0 and a1 is 7, rank 8 is 56 higher
+    for (allowed, symbol, king, rook, colour) in [
+        (white.king_side, 'K', 3, 0, Colour::White),
+        (white.queen_side, 'Q', 3, 7, Colour::White),
+        (black.king_side, 'k', 59, 56, Colour::Black),
+        (black.queen_side, 'q', 59, 63, Colour::Black),
+    ] {
+        if allowed
+            && (board[king] != Some(Piece::King(colour))
+                || board[rook] != Some(Piece::Rook(colour)))
+        {
+            return Err(FenError::CastlingMismatch(symbol));
+        }
+    }
+    Ok(())
+}
+
+/// checks that a pawn of the side that just moved can have double stepped past `square`
+fn check_en_passant(
+    board: &[Option<Piece>; 64],
+    active_colour: Colour,
+    square: usize,
+) -> Result<(), FenError> {
+    // the pawn stands in front of the skipped square, seen from the side that moved it
+    let (pawn, from) = match active_colour {
+        Colour::White => (square - 8, square + 8),
+        Colour::Black => (square + 8, square - 8),
+    };
+    let moved = match active_colour {
+        Colour::White => Piece::Pawn(Colour::Black),
+        Colour::Black => Piece::Pawn(Colour::White),
+    };
+    if board[pawn] != Some(moved) || board[square].is_some() || board[from].is_some() {
+        return Err(FenError::NoEnPassantPawn(
+            Square::from_index(square).unwrap(),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_piece(symbol: char) -> Option<Piece> {
+    let colour = if symbol.is_ascii_uppercase() {
+        Colour::White
+    } else {
+        Colour::Black
+    };
+
+    match symbol.to_ascii_lowercase() {
+        'k' => Some(Piece::King(colour)),
+        'q' => Some(Piece::Queen(colour)),
+        'r' => Some(Piece::Rook(colour)),
+        'b' => Some(Piece::Bishop(colour)),
+        'n' => Some(Piece::Knight(colour)),
+        'p' => Some(Piece::Pawn(colour)),
+        _ => None,
+    }
+}
+
+pub(crate) fn piece_symbol(piece: Piece) -> char {
+    let (symbol, colour) = match piece {
+        Piece::King(colour) => ('k', colour),
+        Piece::Queen(colour) => ('q', colour),
+        Piece::Rook(colour) => ('r', colour),
+        Piece::Bishop(colour) => ('b', colour),
+        Piece::Knight(colour) => ('n', colour),
+        Piece::Pawn(colour) => ('p', colour),
+    };
+
+    match colour {
+        Colour::White => symbol.to_ascii_uppercase(),
+        Colour::Black => symbol,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FenError;
+    use crate::{Colour, Game, GameState, Piece, Square};
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn start_position_round_trip() {
+        assert_eq!(Game::new().to_fen(), START);
+
+        let game = Game::from_fen(START).unwrap();
+        assert_eq!(game.board, Game::new().board);
+        assert_eq!(game.to_fen(), START);
+    }
+
+    #[test]
+    fn fen_after_moves() {
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        game.make_move("g8", "f6");
+        game.make_move("e1", "e2");
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"
+        );
+    }
+
+    #[test]
+    fn load_position() {
+        let game = Game::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 5 20").unwrap();
+
+        assert_eq!(game.board[7], Some(Piece::Rook(Colour::White))); // a1
+        assert_eq!(game.board[35], Some(Piece::Pawn(Colour::White))); // e5
+        assert_eq!(game.get_en_passant(), Some("d6".to_string()));
+        assert!(game.get_castling_rights(Colour::White).king_side);
+        assert!(!game.get_castling_rights(Colour::White).queen_side);
+        assert!(!game.get_castling_rights(Colour::Black).king_side);
+        assert!(game.get_castling_rights(Colour::Black).queen_side);
+        assert_eq!(game.halfmove_clock, 5);
+        assert_eq!(game.fullmove_number, 20);
+    }
+
+    #[test]
+    fn four_fields_default_clocks() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn state_is_evaluated() {
+        // fool's mate
+        let game = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        assert_eq!(game.get_game_state(), GameState::CheckMate);
+    }
+
+    #[test]
+    fn malformed_fen() {
+        assert_eq!(
+            Game::from_fen("8/8 w").err(),
+            Some(FenError::WrongFieldCount(2))
+        );
+        assert_eq!(
+            Game::from_fen("8/8/8/8/8/8/8 w - - 0 1").err(),
+            Some(FenError::WrongRankCount(7))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K4 w - - 0 1").err(),
+            Some(FenError::WrongRankLength { rank: 1, length: 9 })
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4X3 w - - 0 1").err(),
+            Some(FenError::InvalidPiece('X'))
+        );
+        assert_eq!(
+            Game::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").err(),
+            Some(FenError::InvalidKingCount {
+                colour: Colour::Black,
+                count: 0
+            })
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1").err(),
+            Some(FenError::InvalidActiveColour("x".to_string()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 w KK - 0 1").err(),
+            Some(FenError::InvalidCastling("KK".to_string()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - e3 0 1").err(),
+            Some(FenError::InvalidEnPassant("e3".to_string()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").err(),
+            Some(FenError::InvalidHalfmoveClock("x".to_string()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0").err(),
+            Some(FenError::InvalidFullmoveNumber("0".to_string()))
+        );
+    }
+
+    #[test]
+    fn impossible_positions() {
+        assert_eq!(
+            Game::from_fen("3k4/8/8/8/8/8/8/3QK2q w - - 0 1").err(),
+            Some(FenError::InactiveSideInCheck(Colour::Black))
+        );
+        assert_eq!(
+            Game::from_fen("P3k3/8/8/8/8/8/8/4K3 w - -").err(),
+            Some(FenError::PawnOnBackRank(Square::parse("a8").unwrap()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K2p b - -").err(),
+            Some(FenError::PawnOnBackRank(Square::parse("h1").unwrap()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1").err(),
+            Some(FenError::CastlingMismatch('K'))
+        );
+        assert_eq!(
+            Game::from_fen("r3k2r/8/8/8/8/8/8/R2K3R w Qk - 0 1").err(),
+            Some(FenError::CastlingMismatch('Q'))
+        );
+        assert_eq!(
+            Game::from_fen("r3k1r1/8/8/8/8/8/8/R3K2R w Kk - 0 1").err(),
+            Some(FenError::CastlingMismatch('k'))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/4p3/8/8/8/4K3 w - d6 0 1").err(),
+            Some(FenError::NoEnPassantPawn(Square::parse("d6").unwrap()))
+        );
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/4P3/8/4K3/8 b - e3 0 1").err(),
+            Some(FenError::NoEnPassantPawn(Square::parse("e3").unwrap()))
+        );
+        assert!(Game::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+    }
+}
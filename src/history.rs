@@ -0,0 +1,201 @@
+//! Taking back moves and playing them again.
+
+use crate::zobrist;
+use crate::{CastlingRights, Colour, Game, GameState, Move, Piece};
+
+/// A move that was played, together with everything it changed that can't be worked out
+/// from the move itself.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct HistoryEntry {
+    pub(crate) mv: Move,
+    /// the piece that was taken, also for en passant where it isn't standing on `mv.to`
+    pub(crate) captured: Option<Piece>,
+    pub(crate) white_castling: CastlingRights,
+    pub(crate) black_castling: CastlingRights,
+    pub(crate) en_passant: Option<i32>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    pub(crate) state: GameState,
+    /// the hash of the position before the move, see `Game::hash`
+    pub(crate) position_hash: u64,
+    pub(crate) draw_offer: Option<Colour>,
+}
+
+impl Game {
+    /// Takes back the last move and returns it, or `None` if no move has been played.
+    /// The move can be played again with `redo_move` until a different move is made.
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let mv = self.take_back()?;
+        self.restart_clock();
+        self.redo_stack.push(mv);
+        Some(mv)
+    }
+
+    /// Plays the last move taken back by `undo_move` again and returns the resulting state,
+    /// or `None` if there is nothing to redo or the game is over. Like `play`, this presses
+    /// the clock, so a side that has run out of time can't redo its move.
+    pub fn redo_move(&mut self) -> Option<GameState> {
+        if self.is_game_over() {
+            return None;
+        }
+        let mv = *self.redo_stack.last()?;
+        self.press_clock().ok()?;
+        self.redo_stack.pop();
+
+        let state = self.apply_move(mv);
+        if self.is_game_over() {
+            self.stop_clock();
+        }
+        Some(state)
+    }
+
+    /// Returns every move played so far, oldest first.
+    pub fn get_move_history(&self) -> Vec<Move> {
+        self.history.iter().map(|entry| entry.mv).collect()
+    }
+
+    /// takes back the last move without keeping it for `redo_move`
+    pub(crate) fn take_back(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+        let mv = entry.mv;
+        let (from, to) = (mv.from.index(), mv.to.index());
+
+        let colour = match self.active_colour {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        };
+        self.hash ^= self.castling_key() ^ self.en_passant_key();
+
+        let piece = match mv.promotion {
+            Some(_) => Some(Piece::Pawn(colour)),
+            None => self.board[to],
+        };
+        self.set_piece(from, piece);
+        self.set_piece(to, None);
+
+        // the pawn taken en passant stands next to the square the capturing pawn moved to
+        let captured_on = if mv.en_passant {
+            (from / 8) * 8 + to % 8
+        } else {
+            to
+        };
+        self.set_piece(captured_on, entry.captured);
+
+        if mv.castle {
+            let (rook_from, rook_to) = Game::castling_rook_squares(from as i32, to as i32);
+            self.set_piece(rook_from as usize, self.board[rook_to as usize]);
+            self.set_piece(rook_to as usize, None);
+        }
+
+        self.active_colour = colour;
+        self.white_castling = entry.white_castling;
+        self.black_castling = entry.black_castling;
+        self.en_passant = entry.en_passant;
+        self.hash ^= zobrist::black_to_move_key() ^ self.castling_key() ^ self.en_passant_key();
+        self.halfmove_clock = entry.halfmove_clock;
+        self.fullmove_number = entry.fullmove_number;
+        self.state = entry.state;
+        self.draw_offer = entry.draw_offer;
+        // a resignation or agreed draw after the move is taken back with it
+        self.outcome = None;
+        Some(mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Clock, Colour, Game, GameState, ManualTime, Piece, TimeControl};
+
+    #[test]
+    fn undo_and_redo_a_game() {
+        let mut game = Game::new();
+        let mut fens = vec![game.to_fen()];
+        for san in ["e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5", "Nf3", "Nf6"] {
+            game.make_san_move(san);
+            fens.push(game.to_fen());
+        }
+
+        for fen in fens.iter().rev().skip(1) {
+            assert!(game.undo_move().is_some());
+            assert_eq!(&game.to_fen(), fen);
+        }
+        assert_eq!(game.undo_move(), None);
+
+        for fen in fens.iter().skip(1) {
+            assert!(game.redo_move().is_some());
+            assert_eq!(&game.to_fen(), fen);
+        }
+        assert_eq!(game.redo_move(), None);
+        assert_eq!(game.get_move_history().len(), 8);
+    }
+
+    #[test]
+    fn undo_special_moves() {
+        // castling, en passant and a capturing promotion
+        for (fen, san) in [
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O-O"),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "exd6"),
+            ("1r2k3/P7/8/8/8/8/8/4K3 w - - 3 40", "axb8=N"),
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            assert!(game.try_make_san_move(san).is_ok());
+            assert_ne!(game.to_fen(), fen);
+
+            game.undo_move();
+            assert_eq!(game.to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn no_redo_once_game_is_over() {
+        let mut game = Game::new();
+        game.make_san_move("e4");
+        game.undo_move();
+        game.resign(Colour::White).unwrap();
+        assert_eq!(game.redo_move(), None);
+        assert!(game.get_move_history().is_empty());
+
+        // a flag that has fallen ends the game instead of redoing the move
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(Duration::from_secs(60)),
+            time.clone(),
+        ));
+        game.make_san_move("e4");
+        game.undo_move();
+        time.advance(Duration::from_secs(61));
+        assert_eq!(game.redo_move(), None);
+        assert_eq!(game.get_game_state(), GameState::GameOver);
+        assert!(game.get_move_history().is_empty());
+    }
+
+    #[test]
+    fn new_move_clears_redo() {
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        game.undo_move();
+        game.make_move("d2", "d4");
+
+        assert_eq!(game.redo_move(), None);
+        assert_eq!(game.board[28], Some(Piece::Pawn(Colour::White))); // d4
+        assert_eq!(game.board[27], None); // e4
+    }
+
+    #[test]
+    fn undo_checkmate() {
+        let mut game = Game::new();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san);
+        }
+        assert!(game.is_game_over());
+
+        game.undo_move();
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        assert!(game.make_san_move("Qh4").is_some());
+        assert_eq!(game.get_game_state(), GameState::CheckMate);
+    }
+}
@@ -1,11 +1,63 @@
-use core::panic;
-use std::{fmt, string, usize, vec};
+use std::fmt;
+
+use bitboard::{Bitboards, Positions};
+use history::HistoryEntry;
+
+mod bitboard;
+mod clock;
+mod draw;
+mod engine;
+mod error;
+mod eval;
+mod fen;
+mod history;
+mod movegen;
+mod moves;
+mod outcome;
+mod perft;
+mod pgn;
+mod play;
+mod render;
+mod san;
+#[cfg(feature = "serde")]
+mod serialize;
+mod square;
+mod uci;
+mod xboard;
+mod zobrist;
+
+pub use clock::{Bonus, Clock, ManualTime, MonotonicTime, Stage, TimeControl, TimeSource};
+pub use engine::{Engine, Score, SearchLimits, SearchResult};
+pub use error::ChessError;
+pub use eval::{evaluate, Weights, WeightsError};
+pub use fen::FenError;
+pub use movegen::MoveList;
+pub use moves::Move;
+pub use outcome::{Outcome, Termination};
+pub use pgn::{PgnError, PgnGame, PgnMove};
+pub use play::run_play;
+pub use render::{Glyphs, RenderOptions};
+pub use square::Square;
+pub use uci::run_uci;
+pub use xboard::run_xboard;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameState {
     InProgress,
     Check,
     CheckMate,
+    /// the side to move has no legal moves but isn't in check, the game is drawn
+    Stalemate,
+    /// the same position came up five times, or three times and a draw was claimed
+    Repetition,
+    /// seventy-five moves by each side without a capture or pawn move, or fifty and a draw was claimed
+    FiftyMoveRule,
+    /// neither side has enough pieces left to ever give checkmate
+    InsufficientMaterial,
+    /// the game ended in a draw for any other reason, like the players agreeing to one
+    Draw,
+    /// a player resigned or ran out of time, `get_outcome` tells who won
     GameOver,
 }
 
@@ -27,12 +79,14 @@ pub enum GameState {
 /// 7| P  P  P  P  P  P  P  P | BLACK
 /// 8| R  N  B  K  Q  B   N R |
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Colour {
     White,
     Black,
 }
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Piece {
     King(Colour),
     Queen(Colour),
@@ -42,11 +96,56 @@ pub enum Piece {
     Pawn(Colour),
 }
 
+/// Which sides a colour is still allowed to castle towards.
+/// A right is lost for good once the king or the matching rook has moved, or the rook has been captured.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CastlingRights {
+    pub king_side: bool,
+    pub queen_side: bool,
+}
+
+#[derive(Clone)]
 pub struct Game {
     state: GameState,
 
     active_colour: Colour,
     board: [Option<Piece>; 64],
+    /// the same pieces as `board`, for generating moves
+    bitboards: Bitboards,
+    /// the Zobrist hash of the position, kept up to date with every change
+    hash: u64,
+
+    white_castling: CastlingRights,
+    black_castling: CastlingRights,
+
+    /// the square a pawn skipped over with a double step on the last move, where it can be taken en passant
+    en_passant: Option<i32>,
+
+    /// half moves since the last capture or pawn move
+    halfmove_clock: u32,
+    /// starts at 1 and goes up after every move by black
+    fullmove_number: u32,
+
+    /// the position the game was set up from, `None` for the normal starting position
+    start_fen: Option<String>,
+    /// every move made so far, with what is needed to take it back
+    history: Vec<HistoryEntry>,
+    /// moves that were taken back, the next one to redo is last
+    redo_stack: Vec<Move>,
+
+    /// the colour that offered a draw, while the offer stands
+    draw_offer: Option<Colour>,
+    /// how the game ended, for endings that can't be read from the board like resigning
+    outcome: Option<Outcome>,
+    /// the clock for a timed game
+    clock: Option<Clock>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
 impl Game {
@@ -54,12 +153,12 @@ impl Game {
     pub fn new() -> Game {
         let mut bboard: [Option<Piece>; 64] = [None; 64]; // needs better name
 
-        let board_template = "RNBKQBNRPPPPPPPP********************************PPPPPPPPRNBKQBNR "; // imagine a new line every 8 characters
+        let board_template = "RNBKQBNRPPPPPPPP********************************PPPPPPPPRNBKQBNR"; // imagine a new line every 8 characters
 
         let mut current_colour = Colour::White; // 0 = black, 1 = white, might need to change this to include starting position idk
 
-        for i in 0..64 {
-            bboard[i] = match board_template.chars().nth(i).unwrap() {
+        for (i, symbol) in board_template.chars().enumerate() {
+            bboard[i] = match symbol {
                 'R' => Some(Piece::Rook(current_colour)),
                 'N' => Some(Piece::Knight(current_colour)),
                 'B' => Some(Piece::Bishop(current_colour)),
@@ -73,35 +172,60 @@ impl Game {
             }
         }
 
-        Game {
+        let mut game = Game {
             /* initialise board, set active colour to white, ... */
             state: GameState::InProgress,
 
             active_colour: Colour::White,
             board: bboard,
-        }
+            bitboards: Bitboards::from_board(&bboard),
+            hash: 0,
+
+            white_castling: CastlingRights {
+                king_side: true,
+                queen_side: true,
+            },
+            black_castling: CastlingRights {
+                king_side: true,
+                queen_side: true,
+            },
+
+            en_passant: None,
+
+            halfmove_clock: 0,
+            fullmove_number: 1,
+
+            start_fen: None,
+            history: vec![],
+            redo_stack: vec![],
+
+            draw_offer: None,
+            outcome: None,
+            clock: None,
+        };
+        game.hash = game.compute_hash();
+        game
     }
     /// Converts chess notation to position on the board
     /// input should be should be standard chess notation for a single space on the board e.g. "e1" or "e2"
+    /// panics on anything else, use `try_convert_from_notation` for input that hasn't been checked
     pub fn convert_from_notation(notation: &str) -> (i32, i32) {
-        let notation = notation
-            .split_inclusive(char::is_alphabetic)
-            .collect::<Vec<&str>>();
-        let rank = notation[1].parse::<i32>().unwrap() - 1;
-        let file = match notation[0] {
-            // board is backwards
-            "a" => 7,
-            "b" => 6,
-            "c" => 5,
-            "d" => 4,
-            "e" => 3,
-            "f" => 2,
-            "g" => 1,
-            "h" => 0,
-            _ => panic!(),
-        };
+        match Game::try_convert_from_notation(notation) {
+            Ok(position) => position,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Same as `convert_from_notation`, but returns an error for anything that isn't a square.
+    pub fn try_convert_from_notation(notation: &str) -> Result<(i32, i32), ChessError> {
+        let square = Game::parse_square(notation)?;
+        // board is backwards
+        Ok((7 - square.file() as i32, square.rank() as i32))
+    }
 
-        return (file, rank);
+    /// reads a square, turning bad notation into an error
+    fn parse_square(notation: &str) -> Result<Square, ChessError> {
+        Square::parse(notation).ok_or_else(|| ChessError::InvalidNotation(notation.to_string()))
     }
 
     pub fn convert_to_notation(numeric_position: i32) -> String {
@@ -118,11 +242,21 @@ impl Game {
             0 => "h",
             _ => panic!("cannot assign {} to letter", numeric_position % 8),
         };
-        return format!("{}{}", file, rank);
+        format!("{}{}", file, rank)
     }
 
     pub fn get_piece_colour(&self, position: i32) -> Option<Colour> {
-        match self.board[position as usize] {
+        Game::colour_of(self.board[position as usize])
+    }
+
+    /// Get the piece standing on a square, if any.
+    pub fn get_piece(&self, square: Square) -> Option<Piece> {
+        self.board[square.index()]
+    }
+
+    /// Returns the colour of a square's content, if there is a piece on it
+    fn colour_of(piece: Option<Piece>) -> Option<Colour> {
+        match piece {
             Some(Piece::King(colour))
             | Some(Piece::Queen(colour))
             | Some(Piece::Rook(colour))
@@ -133,848 +267,371 @@ impl Game {
         }
     }
 
-    /// If the current game state is `InProgress` and the move is legal,
-    /// move a piece and return the resulting state of the game.
-    /// notation should be <from position><to position> e.g. e1e2 moves the piece at e1 to e2
-    pub fn make_move(&mut self, _from: &str, _to: &str) -> Option<GameState> {
-        if self.get_game_state() == GameState::CheckMate {
-            eprintln!("game is over");
-            return None;
+    /// Get the sides the given colour may still castle towards.
+    pub fn get_castling_rights(&self, colour: Colour) -> CastlingRights {
+        match colour {
+            Colour::White => self.white_castling,
+            Colour::Black => self.black_castling,
         }
+    }
 
-        let from = {
-            let (file, rank) = Game::convert_from_notation(_from);
-            rank * 8 + file
-        };
+    /// Get the square a pawn can currently be taken on en passant, e.g. "e3" right after e2e4.
+    pub fn get_en_passant(&self) -> Option<String> {
+        self.en_passant.map(Game::convert_to_notation)
+    }
 
-        let to = {
-            let (file, rank) = Game::convert_from_notation(_to);
-            rank * 8 + file
-        };
+    /// Returns every legal move of the side to move, with one move per promotion choice.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_moves(&mut moves);
+        moves.to_vec()
+    }
 
-        match Game::get_possible_moves(&self, self.board, _from, self.active_colour) {
-            Some(vector) => {
-                if let Some(legal_moves) = self.get_legal_moves(_from, self.active_colour, vector) {
-                    if legal_moves.contains(&_to.to_string()) {
-                        self.board[to as usize] = self.board[from as usize];
-                        self.board[from as usize] = None;
-                    } else {
-                        eprintln!("illegal move");
-                        return Some(self.get_game_state());
-                    }
-                }
-            }
-            None => return None,
-        }
+    /// Returns the legal moves of the piece on `from`, if it belongs to the side to move.
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_moves(&mut moves);
+        moves.iter().filter(|mv| mv.from == from).copied().collect()
+    }
 
-        if self.active_colour == Colour::White && self.check_checker(Colour::Black, self.board) {
-            if self.checkmate_checker(Colour::Black) {
-                eprintln!("game is over");
-                self.state = GameState::CheckMate;
-                return Some(GameState::CheckMate);
-            } else {
-                self.state = GameState::CheckMate;
-                return Some(GameState::Check);
-            }
-        } else if self.active_colour == Colour::Black
-            && self.check_checker(Colour::White, self.board)
-        {
-            println!("{}", self.checkmate_checker(Colour::White));
-            if self.checkmate_checker(Colour::White) {
-                eprintln!("game is over");
-                self.state = GameState::CheckMate;
-                return Some(GameState::CheckMate);
-            } else {
-                self.state = GameState::CheckMate;
-                return Some(GameState::Check);
-            }
+    /// builds a move with its flags filled in from the current board
+    pub(crate) fn describe_move(&self, from: i32, to: i32, promotion: Option<Piece>) -> Move {
+        let piece = self.board[from as usize];
+        let en_passant = matches!(piece, Some(Piece::Pawn(_)))
+            && self.en_passant == Some(to)
+            && from % 8 != to % 8;
+
+        Move {
+            from: Square::from_index(from as usize).unwrap(),
+            to: Square::from_index(to as usize).unwrap(),
+            promotion,
+            capture: self.board[to as usize].is_some() || en_passant,
+            castle: matches!(piece, Some(Piece::King(_))) && (from - to).abs() == 2,
+            en_passant,
         }
+    }
 
-        if self.active_colour == Colour::White {
-            self.active_colour = Colour::Black
-        } else {
-            self.active_colour = Colour::White
-        }
+    /// If the current game state is `InProgress` and the move is legal,
+    /// move a piece and return the resulting state of the game.
+    /// notation should be <from position><to position> e.g. e1e2 moves the piece at e1 to e2
+    /// castling is done by moving the king two steps towards the rook, e.g. e1g1
+    /// en passant is done by moving the pawn diagonally onto the square the enemy pawn skipped
+    /// a pawn moving to the last rank has to be promoted with `make_move_with_promotion`
+    pub fn make_move(&mut self, _from: &str, _to: &str) -> Option<GameState> {
+        let result = self.try_make_move(_from, _to);
+        self.optional_state(result)
+    }
 
-        return Some(GameState::InProgress);
+    /// Same as `make_move`, but returns why a move was refused.
+    pub fn try_make_move(&mut self, _from: &str, _to: &str) -> Result<GameState, ChessError> {
+        self.play(Move::new(
+            Game::parse_square(_from)?,
+            Game::parse_square(_to)?,
+            None,
+        ))
     }
 
-    /// (Optional but recommended) Set the piece type that a pawn becames following a promotion.
-    pub fn set_promotion(&mut self, _position: &str, _piece: &str) {
-        let (file, rank) = Game::convert_from_notation(_position);
-        let position = rank * 8 + file;
+    /// Same as `make_move`, but for a pawn reaching the last rank.
+    /// piece is "q" for queen, "r" for rook, "n" for knight or "b" for bishop.
+    pub fn make_move_with_promotion(
+        &mut self,
+        _from: &str,
+        _to: &str,
+        _piece: &str,
+    ) -> Option<GameState> {
+        let result = self.try_make_move_with_promotion(_from, _to, _piece);
+        self.optional_state(result)
+    }
 
-        if self.board[position as usize].is_some() {
-            if self.board[position as usize] != Some(Piece::Pawn(self.active_colour)) {
-                eprintln!("you can only promote pawns")
-            } else {
-                match _piece {
-                    "q" => self.board[position as usize] = Some(Piece::Queen(self.active_colour)),
-                    "r" => self.board[position as usize] = Some(Piece::Queen(self.active_colour)),
-                    "n" => self.board[position as usize] = Some(Piece::Queen(self.active_colour)),
-                    "b" => self.board[position as usize] = Some(Piece::Queen(self.active_colour)),
-                    _ => return,
-                }
-            }
-        }
+    /// Same as `make_move_with_promotion`, but returns why a move was refused.
+    pub fn try_make_move_with_promotion(
+        &mut self,
+        _from: &str,
+        _to: &str,
+        _piece: &str,
+    ) -> Result<GameState, ChessError> {
+        let from = Game::parse_square(_from)?;
+        let to = Game::parse_square(_to)?;
+        let piece = Game::promotion_piece(_piece, self.active_colour)
+            .ok_or_else(|| ChessError::InvalidPromotionPiece(_piece.to_string()))?;
+        self.play(Move::new(from, to, Some(piece)))
     }
 
-    /// Get the current game state.
-    pub fn get_game_state(&self) -> GameState {
-        self.state
+    /// turns the result of a move into what the `Option` based functions have always returned:
+    /// nothing when no move could be attempted, the unchanged state when the move was illegal
+    fn optional_state(&self, result: Result<GameState, ChessError>) -> Option<GameState> {
+        match result {
+            Ok(state) => Some(state),
+            Err(
+                ChessError::GameOver
+                | ChessError::EmptySquare(_)
+                | ChessError::WrongColour(_)
+                | ChessError::InvalidNotation(_),
+            ) => None,
+            Err(_) => Some(self.state),
+        }
     }
 
-    /// takes position where king is standing or will be standing and returns true if that space is threatened
-    fn check_checker(&self, checking_for: Colour, board: [Option<Piece>; 64]) -> bool {
-        // check for pieces that can reach the king directly
+    /// If the current game state is `InProgress` and the move is legal, make the move and
+    /// return the resulting state of the game. Only `from`, `to` and `promotion` of the move
+    /// are looked at, so it can come from `legal_moves` or be made with `Move::new`.
+    pub fn play(&mut self, mv: Move) -> Result<GameState, ChessError> {
+        if self.is_game_over() {
+            return Err(ChessError::GameOver);
+        }
 
-        let mut position = 0; //needs a value for the compiler to shut up
+        let from = mv.from.index() as i32;
+        let to = mv.to.index() as i32;
 
-        // if the current piece isn't the king, find king and run program
+        match Game::colour_of(self.board[from as usize]) {
+            Some(colour) if colour == self.active_colour => (),
+            Some(_) => return Err(ChessError::WrongColour(mv.from)),
+            None => return Err(ChessError::EmptySquare(mv.from)),
+        }
 
-        for i in 0..board.len() {
-            if board[i] == Some(Piece::King(checking_for)) {
-                position = i as i32;
-                break;
-            }
+        if self.legal_targets(from as usize, self.active_colour) & (1 << to) == 0 {
+            return Err(ChessError::IllegalMove(mv));
         }
-        // if there is a legal move threatening the king
-        for piece in 0..board.len() {
-            if board[piece].is_some() {
-                if self.get_piece_colour(piece as i32) != Some(checking_for) {
-                    let other_c: Colour = match checking_for {
-                        Colour::White => Colour::Black,
-                        Colour::Black => Colour::White,
-                    };
-
-                    if let Some(enemy_positions) = Game::get_possible_moves(
-                        &self,
-                        board,
-                        &Game::convert_to_notation(piece as i32),
-                        other_c,
-                    ) {
-                        if enemy_positions.contains(&Game::convert_to_notation(position)) {
-                            return true;
-                        }
-                    }
-                }
+
+        let promoting = self.board[from as usize] == Some(Piece::Pawn(self.active_colour))
+            && (to / 8 == 0 || to / 8 == 7);
+        if promoting && mv.promotion.is_none() {
+            return Err(ChessError::PromotionRequired(mv));
+        }
+        if !promoting && mv.promotion.is_some() {
+            return Err(ChessError::InvalidPromotion(mv));
+        }
+        if let Some(piece) = mv.promotion {
+            if Game::colour_of(Some(piece)) != Some(self.active_colour)
+                || matches!(piece, Piece::King(_) | Piece::Pawn(_))
+            {
+                return Err(ChessError::InvalidPromotion(mv));
             }
         }
-        return false;
-    }
 
-    /// finds all possible moves for white/black and returns true if there are none (checkmate)
-    pub fn checkmate_checker(&self, checking_for: Colour) -> bool {
-        let mut all_moves: Vec<Vec<String>> = vec![];
-        // all pieces
-        for (index, piece) in self.board.iter().enumerate() {
-            if self.get_piece_colour(index as i32) != Some(checking_for) {
-                continue;
-            }
-            if let Some(possible_moves) = self.get_possible_moves(
-                self.board,
-                &Game::convert_to_notation(index as i32),
-                checking_for,
-            ) {
-                match piece {
-                    Some(piece) => {
-                        if let Some(move_set) = self.get_legal_moves(
-                            &Game::convert_to_notation(index as i32),
-                            checking_for,
-                            possible_moves,
-                        ) {
-                            if !move_set.is_empty() {
-                                all_moves.push(move_set);
-                            }
-                        }
-                    }
-                    None => continue,
-                }
-            }
+        self.press_clock()?;
+        self.redo_stack.clear();
+        let state = self.apply_move(mv);
+        if self.is_game_over() {
+            self.stop_clock();
         }
+        Ok(state)
+    }
 
-        // return true if there are no legal moves
-        if all_moves.is_empty() {
-            return true;
+    /// makes a move that is known to be legal and records it in the history
+    fn apply_move(&mut self, mv: Move) -> GameState {
+        let (from, to) = (mv.from.index() as i32, mv.to.index() as i32);
+        let mv = self.describe_move(from, to, mv.promotion);
+        let captured_on = if mv.en_passant {
+            (from / 8) * 8 + to % 8
         } else {
-            return false;
+            to
+        };
+        self.history.push(HistoryEntry {
+            mv,
+            captured: self.board[captured_on as usize],
+            white_castling: self.white_castling,
+            black_castling: self.black_castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            state: self.state,
+            position_hash: self.hash,
+            draw_offer: self.draw_offer,
+        });
+
+        // moving instead of answering a draw offer declines it
+        if self.draw_offer.is_some() && self.draw_offer != Some(self.active_colour) {
+            self.draw_offer = None;
         }
-    }
 
-    pub fn get_possible_moves(
-        &self,
-        board: [Option<Piece>; 64],
-        _position: &str,
-        checking_for: Colour,
-    ) -> Option<Vec<String>> {
-        // reminder: position is "<file><rank>"
-        // there's probably a better solution
+        // the castling and en passant keys are worked out again once the move is made
+        self.hash ^= self.castling_key() ^ self.en_passant_key();
 
-        let (file, rank) = Game::convert_from_notation(_position);
-        let position = rank * 8 + file; // formula for getting position in the 1D array
+        let piece = self.board[from as usize];
+        let double_step = piece == Some(Piece::Pawn(self.active_colour)) && (from - to).abs() == 16;
+        let pawn_move_or_capture = mv.capture || piece == Some(Piece::Pawn(self.active_colour));
 
-        match board[position as usize] {
-            Some(Piece::King(colour))
-            | Some(Piece::Queen(colour))
-            | Some(Piece::Rook(colour))
-            | Some(Piece::Knight(colour))
-            | Some(Piece::Bishop(colour))
-            | Some(Piece::Pawn(colour)) => {
-                if colour != checking_for {
-                    eprintln!("not your piece");
-                    return None;
-                }
-            }
-            None => {
-                eprintln!("no piece");
-                return None;
-            }
+        // castling moves the rook as well
+        if mv.castle {
+            let (rook_from, rook_to) = Game::castling_rook_squares(from, to);
+            self.set_piece(rook_to as usize, self.board[rook_from as usize]);
+            self.set_piece(rook_from as usize, None);
         }
+        // en passant, the captured pawn stands next to the moving pawn rather than on the target square
+        if mv.en_passant {
+            self.set_piece(captured_on as usize, None);
+        }
+        self.set_piece(to as usize, mv.promotion.or(piece));
+        self.set_piece(from as usize, None);
 
-        let mut potential_moves: Vec<String> = vec![];
-
-        let mut new_pos: i32;
-
-        match board[position as usize] {
-            Some(Piece::Pawn(Colour::White)) => {
-                if 8 < position && position <= 16 {
-                    // if pawn hasn't been moved
-                    new_pos = position + 8;
-                    if board[(position + 8) as usize].is_none() {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
+        if pawn_move_or_capture {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.active_colour == Colour::Black {
+            self.fullmove_number += 1;
+        }
 
-                        new_pos = position + 16; // two steps forward
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                } else {
-                    new_pos = position + 8;
-                    if board[new_pos as usize].is_none() {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
-                // check for capturable pieces
-                if board[(position + 8 + 1) as usize].is_some() {
-                    if self.get_piece_colour(position + 8 - 1) != Some(checking_for) {
-                        potential_moves
-                            .push(format!("{}", Game::convert_to_notation(position + 8 - 1)));
-                    }
-                }
-                if board[(position + 8 - 1) as usize].is_some() {
-                    if self.get_piece_colour(position + 8 + 1) != Some(checking_for) {
-                        potential_moves
-                            .push(format!("{}", Game::convert_to_notation(position + 8 + 1)));
-                    }
-                }
-            }
-            Some(Piece::Pawn(Colour::Black)) => {
-                if 48 < position && position <= 56 {
-                    // if pawn hasn't been moved
-                    new_pos = position - 8;
-                    if board[(position - 8) as usize].is_none() {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-
-                        new_pos = position - 16;
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    }
-                } else {
-                    new_pos = position - 8;
-                    if board[new_pos as usize].is_none() {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+        self.en_passant = if double_step {
+            Some((from + to) / 2)
+        } else {
+            None
+        };
+        self.update_castling_rights(from, to);
 
-                if board[(position - 8 + 1) as usize].is_some() {
-                    if self.get_piece_colour(position + 8 - 1) != Some(checking_for) {
-                        potential_moves
-                            .push(format!("{}", Game::convert_to_notation(position - 8 - 1)));
-                    }
-                }
+        if self.active_colour == Colour::White {
+            self.active_colour = Colour::Black
+        } else {
+            self.active_colour = Colour::White
+        }
+        self.hash ^= zobrist::black_to_move_key() ^ self.castling_key() ^ self.en_passant_key();
 
-                if board[(position - 8 - 1) as usize].is_some() {
-                    if self.get_piece_colour(position + 8 + 1) != Some(checking_for) {
-                        potential_moves
-                            .push(format!("{}", Game::convert_to_notation(position - 8 + 1)));
-                    }
-                }
-            }
-            Some(Piece::Rook(_colour)) => {
-                // vertical movement
-                // up
-                for y in (0..rank).rev() {
-                    new_pos = y * 8 + file;
-
-                    // check if something's in the way
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Rook(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
+        self.update_state();
+        self.state
+    }
 
-                    if file <= new_pos {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
-                // down
-                for y in rank..8 {
-                    new_pos = y * 8 + file;
-
-                    // check if something's in the way
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Rook(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
+    /// Turns a pawn of the side to move standing on the last rank into the piece given as
+    /// "q", "r", "n" or "b". Moves that reach the last rank promote by themselves through
+    /// `make_move_with_promotion`, so this is only needed for a position set up by hand.
+    pub fn set_promotion(&mut self, position: &str, piece: &str) {
+        let _ = self.try_set_promotion(position, piece);
+    }
 
-                    if new_pos <= 7 * 8 + file {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+    /// Same as `set_promotion`, but returns why the pawn couldn't be promoted.
+    pub fn try_set_promotion(&mut self, position: &str, piece: &str) -> Result<(), ChessError> {
+        let square = Game::parse_square(position)?;
+        let piece = Game::promotion_piece(piece, self.active_colour)
+            .ok_or_else(|| ChessError::InvalidPromotionPiece(piece.to_string()))?;
+        self.promote(square, piece)
+    }
 
-                // horizontal movement
-
-                // left
-                for x in (0..file).rev() {
-                    new_pos = rank * 8 + x;
-
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Rook(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
-                    if rank * 8 <= new_pos {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+    /// Turns a pawn of the side to move standing on the last rank into a queen, rook, bishop
+    /// or knight of the same colour, and updates the state of the game for the new piece.
+    pub fn promote(&mut self, square: Square, piece: Piece) -> Result<(), ChessError> {
+        if self.is_game_over() {
+            return Err(ChessError::GameOver);
+        }
+        let letter = fen::piece_symbol(piece).to_ascii_lowercase().to_string();
+        if Game::promotion_piece(&letter, self.active_colour) != Some(piece) {
+            return Err(ChessError::InvalidPromotionPiece(letter));
+        }
 
-                // right
-                for x in file..8 {
-                    new_pos = rank * 8 + x;
-
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Rook(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
-                    if new_pos < (rank + 1) * 8 {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
-            }
-            Some(Piece::Bishop(_colour)) => {
-                for i in 0..8 {
-                    new_pos = (rank + i) * 8 + file + i;
-
-                    let current_file = new_pos - (rank + i) * 8;
-                    let current_rank = (new_pos - (file + i)) / 8;
-
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        // check if something's in the way
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if (board[new_pos as usize]) == Some(Piece::Bishop(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
-                for i in 0..8 {
-                    new_pos = (rank + i) * 8 + file - i;
-
-                    // check if something's in the way
-                    let current_file = new_pos - (rank + i) * 8;
-                    let current_rank = (new_pos - (file - i)) / 8;
-
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if board[new_pos as usize] == Some(Piece::Bishop(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
-                for i in 0..8 {
-                    new_pos = (rank - i) * 8 + file - i;
-
-                    let current_file = new_pos - (rank - i) * 8;
-                    let current_rank = (new_pos - (file - i)) / 8;
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        // check if something's in the way
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if (board[new_pos as usize]) == Some(Piece::Bishop(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
-                eprintln!("backward-right");
-                for i in 0..8 {
-                    new_pos = (rank - i) * 8 + file + i;
-
-                    let current_file = new_pos - (rank - i) * 8;
-                    let current_rank = (new_pos - (file + i)) / 8;
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        // check if something's in the way
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if (board[new_pos as usize]) == Some(Piece::Bishop(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
+        let last_rank = match self.active_colour {
+            Colour::White => 7,
+            Colour::Black => 0,
+        };
+        match self.board[square.index()] {
+            None => return Err(ChessError::EmptySquare(square)),
+            Some(pawn) if pawn != Piece::Pawn(self.active_colour) => {
+                return Err(ChessError::NotAPawn(square))
             }
+            Some(_) if square.rank() != last_rank => return Err(ChessError::NotOnLastRank(square)),
+            Some(_) => (),
+        }
 
-            Some(Piece::Queen(_colour)) => {
-                //Diagonal movement
-                for i in 0..8 {
-                    new_pos = (rank + i) * 8 + file + i;
-
-                    let current_file = new_pos - (rank + i) * 8;
-                    let current_rank = (new_pos - (file + i)) / 8;
-
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        // check if something's in the way
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if (board[new_pos as usize]) == Some(Piece::Queen(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        // eprintln!("({}, {})", file + i, rank + i)
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
-                for i in 0..8 {
-                    new_pos = (rank + i) * 8 + file - i;
-
-                    // check if something's in the way
-                    let current_file = new_pos - (rank + i) * 8;
-                    let current_rank = (new_pos - (file - i)) / 8;
-
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if board[new_pos as usize] == Some(Piece::Queen(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        // eprintln!("({}, {})", file - i, rank + i)
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
-                for i in 0..8 {
-                    new_pos = (rank - i) * 8 + file - i;
-
-                    let current_file = new_pos - (rank - i) * 8;
-                    let current_rank = (new_pos - (file - i)) / 8;
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        // check if something's in the way
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if (board[new_pos as usize]) == Some(Piece::Queen(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        // eprintln!("({}, {})", file - i, rank - i)
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
-                //backward-right
-                for i in 0..8 {
-                    new_pos = (rank - i) * 8 + file + i;
-
-                    let current_file = new_pos - (rank - i) * 8;
-                    let current_rank = (new_pos - (file + i)) / 8;
-                    if (0 <= current_file && current_file <= 7)
-                        && (0 <= current_rank && current_rank <= 7)
-                    {
-                        // check if something's in the way
-                        if board[new_pos as usize].is_some() {
-                            if self.get_piece_colour(new_pos) == Some(checking_for) {
-                                if (board[new_pos as usize]) == Some(Piece::Queen(checking_for)) {
-                                    continue;
-                                } // ignore self
-                                  // break if friendly piece
-                                break;
-                            } else {
-                                potential_moves
-                                    .push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                                break;
-                            }
-                        }
-
-                        // eprintln!("({}, {})", file + i, rank - i)
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)))
-                    } else {
-                        break;
-                    }
-                }
+        // without the pawn, an en passant capture may not be possible anymore
+        let en_passant = self.en_passant_key();
+        self.set_piece(square.index(), Some(piece));
+        self.hash ^= en_passant ^ self.en_passant_key();
+        self.update_state();
+        Ok(())
+    }
 
-                // vertucak movement
-
-                for y in (0..rank).rev() {
-                    new_pos = y * 8 + file;
-
-                    // check if something's in the way
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Queen(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
+    /// turns "q", "r", "n" or "b" into the matching piece of the given colour
+    fn promotion_piece(piece: &str, colour: Colour) -> Option<Piece> {
+        match piece {
+            "q" => Some(Piece::Queen(colour)),
+            "r" => Some(Piece::Rook(colour)),
+            "n" => Some(Piece::Knight(colour)),
+            "b" => Some(Piece::Bishop(colour)),
+            _ => None,
+        }
+    }
 
-                    if file <= new_pos {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
-                // down
-                for y in rank..8 {
-                    new_pos = y * 8 + file;
-
-                    // check if something's in the way
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Queen(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
+    /// Get the current game state.
+    pub fn get_game_state(&self) -> GameState {
+        self.state
+    }
 
-                    if new_pos <= 7 * 8 + file {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+    /// Returns true once the game has ended and no more moves are accepted.
+    pub fn is_game_over(&self) -> bool {
+        matches!(
+            self.state,
+            GameState::CheckMate
+                | GameState::Stalemate
+                | GameState::Repetition
+                | GameState::FiftyMoveRule
+                | GameState::InsufficientMaterial
+                | GameState::Draw
+                | GameState::GameOver
+        )
+    }
 
-                // horizontal movement
-
-                // left
-                for x in (0..file).rev() {
-                    new_pos = rank * 8 + x;
-
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Queen(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
-                    if rank * 8 <= new_pos {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+    /// works out the game state for the side that is about to play
+    fn update_state(&mut self) {
+        let in_check = self.check_checker(self.active_colour);
+        let no_legal_moves = self.checkmate_checker(self.active_colour);
+        self.state = match (in_check, no_legal_moves) {
+            (true, true) => GameState::CheckMate,
+            (true, false) => GameState::Check,
+            (false, true) => GameState::Stalemate,
+            (false, false) => GameState::InProgress,
+        };
 
-                // right
-                for x in file..8 {
-                    new_pos = rank * 8 + x;
-
-                    if board[new_pos as usize].is_some() {
-                        if self.get_piece_colour(new_pos) == Some(checking_for) {
-                            // break if friendly piece
-                            if board[new_pos as usize] == Some(Piece::Queen(checking_for)) {
-                                continue;
-                            }
-                            break;
-                        } else {
-                            potential_moves.push(format!("{}", Game::convert_to_notation(new_pos))); // add move and break if enemy piece
-                            break;
-                        }
-                    }
-                    if new_pos < (rank + 1) * 8 {
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+        // some draws end the game without anyone claiming them, unless the last move gave checkmate
+        if matches!(self.state, GameState::Check | GameState::InProgress) {
+            if let Some(state) = self.automatic_draw() {
+                self.state = state;
             }
+        }
+    }
 
-            Some(Piece::Knight(_colour)) => {
-                // forward
-                if (rank + 2) * 8 < 7 * 8 + file {
-                    // right
-                    if file + 1 < 8 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank + 2) * 8 + file + 1)
-                        ));
-                    }
-                    // left
-                    if file - 1 >= 0 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank + 2) * 8 + file - 1)
-                        ));
-                    }
-                }
-                // backwards
-                if (rank - 2) * 8 >= file {
-                    //right
-                    if file + 1 < 8 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank - 2) * 8 + file + 1)
-                        ));
-                    }
-                    // left
-                    if file - 1 >= 0 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank - 2) * 8 + file - 1)
-                        ));
-                    }
-                }
-                // right
-                if file + 2 < 8 {
-                    // forward
-                    if (rank + 1) * 8 < 7 * 8 + file + 2 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank + 1) * 8 + file + 2)
-                        ));
-                    }
-                    // backward
-                    if (rank - 1) * 8 + file > file + 2 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank - 1) * 8 + file + 2)
-                        ));
-                    }
-                }
-                // left
-                if file - 2 >= 0 {
-                    //backward
-                    if (rank - 1) * 8 + file - 2 > 7 * 8 + file - 2 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank - 1) * 8 + file - 2)
-                        ));
-                    }
-                    // forward
-                    if (rank + 1) * 8 < 7 * 8 + file - 2 {
-                        potential_moves.push(format!(
-                            "{}",
-                            Game::convert_to_notation((rank + 1) * 8 + file - 2)
-                        ));
-                    }
-                }
-
-                let mut temp: Vec<String> = vec![];
-
-                // remove moves that collide with friendly pieces
-                for i in 0..potential_moves.len() {
-                    let (file, rank) = Game::convert_from_notation(&potential_moves[i]);
-                    // if there is a piece and it's hostile, make it capturable
-                    if board[(rank * 8 + file) as usize].is_some() {
-                        if self.get_piece_colour(rank * 8 + file) != Some(checking_for) {
-                            temp.push((&potential_moves[i]).to_string());
-                        }
-                        // if there is no piece you can obviously go there
-                    } else {
-                        temp.push((&potential_moves[i]).to_string())
-                    }
-                }
-                potential_moves = temp;
-            }
+    /// returns true if the king of the given colour is threatened
+    fn check_checker(&self, checking_for: Colour) -> bool {
+        self.bitboards.in_check(checking_for)
+    }
 
-            Some(Piece::King(_colour)) => {
-                // 1 step back
-                if position - 1 * 8 > 0 {
-                    new_pos = position - 8;
-                    potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-
-                    // 1 step back-left
-                    if (rank - 2) * 8 < position - 1 * 8 - 1 {
-                        new_pos = position - 8 - 1;
-                        // check if target position is within the previous line
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                    // 1 step back-right
-                    if position - 8 + 1 > (rank - 1) * 8 {
-                        new_pos = position - 8 + 1;
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
-                // 1 step forward
-                if position + 8 < 8 * 8 {
-                    new_pos = position + 8;
-                    potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-
-                    // 1 step forward-left
-                    if position + 8 - 1 > (rank + 1) * 8 {
-                        new_pos = position + 8 - 1;
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                    // 1 step forward-right
-                    if position + 8 + 1 < (rank + 2) * 8 - 1 {
-                        new_pos = position + 8 + 1;
-                        potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                    }
-                }
+    /// finds all possible moves for white/black and returns true if there are none (checkmate)
+    pub fn checkmate_checker(&self, checking_for: Colour) -> bool {
+        // return true if there are no legal moves
+        !Positions(self.bitboards.colour(checking_for))
+            .any(|position| self.legal_targets(position, checking_for) != 0)
+    }
 
-                // 1 step left
-                if position - 1 > (rank - 1) * 8 {
-                    new_pos = position - 1;
-                    potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                }
-                // 1 step right
-                if position + 1 < (rank + 1) * 8 {
-                    new_pos = position + 1;
-                    potential_moves.push(format!("{}", Game::convert_to_notation(new_pos)));
-                } else {
-                    return None;
-                }
+    /// If a piece of the given colour is standing on the given tile, return all possible new positions
+    /// the piece can reach, including castling for a king that is still allowed to castle.
+    pub fn get_possible_moves(
+        &self,
+        board: [Option<Piece>; 64],
+        _position: &str,
+        checking_for: Colour,
+    ) -> Option<Vec<String>> {
+        self.try_get_possible_moves(board, _position, checking_for)
+            .ok()
+    }
 
-                let mut temp: Vec<String> = vec![]; // temporary storage of moves
-                                                    // only allow taking friendly pieces
-                for i in 0..potential_moves.len() {
-                    let (file, rank) = Game::convert_from_notation(&potential_moves[i]);
-                    // if there is a piece and it's hostile, make it capturable
-                    if board[(rank * 8 + file) as usize].is_some() {
-                        if self.get_piece_colour(rank * 8 + file) != Some(checking_for) {
-                            temp.push((&potential_moves[i]).to_string());
-                        }
-                        // if there is no piece you can obviously go there
-                    } else {
-                        temp.push((&potential_moves[i]).to_string())
-                    }
-                }
-                potential_moves = temp;
-            }
-            _ => panic!("{:?}", board[position as usize]),
+    /// Same as `get_possible_moves`, but returns why there are no moves to look at.
+    pub fn try_get_possible_moves(
+        &self,
+        board: [Option<Piece>; 64],
+        _position: &str,
+        checking_for: Colour,
+    ) -> Result<Vec<String>, ChessError> {
+        let square = Game::parse_square(_position)?;
+        let position = square.index();
+
+        match Game::colour_of(board[position]) {
+            Some(colour) if colour == checking_for => (),
+            Some(_) => return Err(ChessError::WrongColour(square)),
+            None => return Err(ChessError::EmptySquare(square)),
         }
 
-        return Some(potential_moves);
+        let targets = self.pseudo_targets(&Bitboards::from_board(&board), position, checking_for);
+        Ok(Positions(targets)
+            .map(|to| Game::convert_to_notation(to as i32))
+            .collect())
     }
 
     /// takes potential moves and removes the ones that would result in putting onself in check
@@ -982,100 +639,107 @@ impl Game {
         &self,
         _position: &str,
         checking_for: Colour,
-        mut possible_moves: Vec<String>,
+        possible_moves: Vec<String>,
     ) -> Option<Vec<String>> {
-        let mut legal_moves = vec![];
-
-        for (index, to) in possible_moves.iter().enumerate() {
-            // let mut fake_board = temp.clone();
-            match self.make_fake_move(_position, to, checking_for, &possible_moves) {
-                Some(fake_board) => {
-                    if self.check_checker(checking_for, fake_board) == false {
-                        legal_moves.push(to.to_string());
-                    }
-                }
-                None => (),
-            }
-        }
-        return Some(legal_moves);
+        self.try_get_legal_moves(_position, checking_for, possible_moves)
+            .ok()
     }
 
-    // simulate move to see if it leaves the king in check
-    fn make_fake_move(
+    /// Same as `get_legal_moves`, but returns an error for notation that isn't a square.
+    pub fn try_get_legal_moves(
         &self,
-        _from: &str,
-        _to: &str,
+        _position: &str,
         checking_for: Colour,
-        considered_moves: &Vec<String>,
-    ) -> Option<[Option<Piece>; 64]> {
-        if considered_moves.is_empty() {
-            // return Some(self.get_game_state());
-            return None;
+        possible_moves: Vec<String>,
+    ) -> Result<Vec<String>, ChessError> {
+        let from = Game::parse_square(_position)?.index();
+
+        let mut legal_moves = vec![];
+        for to in possible_moves {
+            let to = Game::parse_square(&to)?.index();
+            if self.leaves_king_safe(&self.bitboards, from, to, checking_for) {
+                legal_moves.push(Game::convert_to_notation(to as i32));
+            }
         }
-        let from = {
-            let (file, rank) = Game::convert_from_notation(_from);
-            rank * 8 + file
-        };
+        Ok(legal_moves)
+    }
 
-        let to = {
-            let (file, rank) = Game::convert_from_notation(_to);
-            rank * 8 + file
-        };
+    /// every position the piece on `position` can legally move to on the current board
+    pub(crate) fn legal_destinations(&self, position: i32, checking_for: Colour) -> Vec<i32> {
+        Positions(self.legal_targets(position as usize, checking_for))
+            .map(|to| to as i32)
+            .collect()
+    }
 
-        let mut fake_board = self.board.clone(); // copy of the board
-        match considered_moves {
-            _vector => {
-                fake_board[to as usize] = fake_board[from as usize];
-                fake_board[from as usize] = None;
-                return Some(fake_board);
+    /// takes the king's from and to position of a castling move and returns where the rook moves from and to
+    fn castling_rook_squares(from: i32, to: i32) -> (i32, i32) {
+        if to < from {
+            // king side
+            (from - 3, from - 1)
+        } else {
+            // queen side
+            (from + 4, from + 1)
+        }
+    }
+
+    /// removes castling rights when a king or rook leaves its starting square, or a rook is captured there
+    fn update_castling_rights(&mut self, from: i32, to: i32) {
+        for position in [from, to] {
+            match position {
+                3 => {
+                    self.white_castling.king_side = false;
+                    self.white_castling.queen_side = false;
+                }
+                0 => self.white_castling.king_side = false,
+                7 => self.white_castling.queen_side = false,
+                59 => {
+                    self.black_castling.king_side = false;
+                    self.black_castling.queen_side = false;
+                }
+                56 => self.black_castling.king_side = false,
+                63 => self.black_castling.queen_side = false,
+                _ => (),
             }
         }
     }
+
+    /// puts a piece on a square of both the board and the bitboards, or empties it
+    pub(crate) fn set_piece(&mut self, position: usize, piece: Option<Piece>) {
+        if let Some(old) = self.board[position] {
+            self.hash ^= zobrist::piece_key(old, position);
+        }
+        if let Some(new) = piece {
+            self.hash ^= zobrist::piece_key(new, position);
+        }
+        self.board[position] = piece;
+        self.bitboards.set(position, piece);
+    }
+
+    /// replaces every piece on the board
+    pub(crate) fn set_board(&mut self, board: [Option<Piece>; 64]) {
+        self.board = board;
+        self.bitboards = Bitboards::from_board(&board);
+        self.hash = self.compute_hash();
+    }
 }
 
-/// Implement print routine for Game.
+/// Implement print routine for Game, the same board as `Display` draws.
 ///
 /// Output example:
-/// |:----------------------:|
-/// | R  N  B  K  Q  B  N  R |
-/// | P  P  P  P  P  P  P  P |
-/// | *  *  *  *  *  *  *  * |
-/// | *  *  *  *  *  *  *  * |
-/// | *  *  *  *  *  *  *  * |
-/// | *  *  *  *  *  *  *  * |
-/// | P  P  P  P  P  P  P  P |
-/// | R  N  B  K  Q  B   N R |
-/// |:----------------------:|
+/// ```text
+/// 8  ♜  ♞  ♝  ♛  ♚  ♝  ♞  ♜
+/// 7  ♟  ♟  ♟  ♟  ♟  ♟  ♟  ♟
+/// 6  ·  ·  ·  ·  ·  ·  ·  ·
+/// 5  ·  ·  ·  ·  ·  ·  ·  ·
+/// 4  ·  ·  ·  ·  ·  ·  ·  ·
+/// 3  ·  ·  ·  ·  ·  ·  ·  ·
+/// 2  ♙  ♙  ♙  ♙  ♙  ♙  ♙  ♙
+/// 1  ♖  ♘  ♗  ♕  ♔  ♗  ♘  ♖
+///    a  b  c  d  e  f  g  h
+/// ```
 impl fmt::Debug for Game {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        /* build board representation string */
-
-        let game = self;
-
-        let mut board_string = String::new();
-        board_string += "\n";
-
-        for rank in 0..game.board.len() {
-            // for piece in game.board[rank] {
-            let piece = game.board[rank];
-            match piece {
-                // get better variable names
-                Some(Piece::King(_)) => board_string += "♔ ",
-                Some(Piece::Queen(_)) => board_string += "♕ ",
-                Some(Piece::Rook(_)) => board_string += "♖ ",
-                Some(Piece::Knight(_)) => board_string += "♘ ",
-                Some(Piece::Bishop(_)) => board_string += "♗ ",
-                Some(Piece::Pawn(_)) => board_string += "♙ ",
-                None => board_string += "* ",
-            };
-            // }
-            if (rank + 1) % 8 == 0 && rank != 0 {
-                // println!("{}", rank);
-                board_string += "\n";
-            }
-        }
-
-        write!(f, "{}", board_string)
+        f.write_str(&self.render(&RenderOptions::default()))
     }
 }
 
@@ -1085,10 +749,11 @@ impl fmt::Debug for Game {
 
 #[cfg(test)]
 mod tests {
-    use core::panic;
-
+    use crate::ChessError;
     use crate::Colour;
+    use crate::Move;
     use crate::Piece;
+    use crate::Square;
 
     use super::Game;
     use super::GameState;
@@ -1136,29 +801,25 @@ mod tests {
 
         let mut all_moves: Vec<Vec<String>> = vec![];
         // all pieces
-        for (index, piece) in game.board.iter().enumerate() {
+        for index in 0..game.board.len() {
             if let Some(possible_moves) = game.get_possible_moves(
                 game.board,
                 &Game::convert_to_notation(index as i32),
                 game.active_colour,
             ) {
-                match piece {
-                    Some(piece) => {
-                        if let Some(temp) = (game.get_legal_moves(
-                            &Game::convert_to_notation(index as i32),
-                            game.active_colour,
-                            possible_moves,
-                        )) {
-                            if !temp.is_empty() {
-                                all_moves.push(temp);
-                            }
-                        }
+                if let Some(temp) = game.get_legal_moves(
+                    &Game::convert_to_notation(index as i32),
+                    game.active_colour,
+                    possible_moves,
+                ) {
+                    if !temp.is_empty() {
+                        all_moves.push(temp);
                     }
-                    None => continue,
                 }
             }
         }
-        println!("{:?}", all_moves);
+        assert_eq!(all_moves.len(), 10);
+        assert_eq!(all_moves.iter().map(Vec::len).sum::<usize>(), 20);
     }
 
     #[test]
@@ -1184,8 +845,7 @@ mod tests {
         game.make_move("f2", "f3");
         game.make_move("d8", "h4");
 
-        println!("{:?}", game);
-        println!("{:?}", game.get_game_state());
+        assert_eq!(game.get_game_state(), GameState::CheckMate);
     }
 
     #[test]
@@ -1206,4 +866,436 @@ mod tests {
 
         assert_eq!(in_check, true)
     } */
+
+    /// puts pieces on an otherwise empty board, e.g. `&[("e1", Piece::King(Colour::White))]`
+    fn game_with_pieces(pieces: &[(&str, Piece)]) -> Game {
+        let mut game = Game::new();
+        game.set_board([None; 64]);
+        for (position, piece) in pieces {
+            let (file, rank) = Game::convert_from_notation(position);
+            game.set_piece((rank * 8 + file) as usize, Some(*piece));
+        }
+        game
+    }
+
+    fn castling_position() -> Game {
+        game_with_pieces(&[
+            ("e1", Piece::King(Colour::White)),
+            ("a1", Piece::Rook(Colour::White)),
+            ("h1", Piece::Rook(Colour::White)),
+            ("e8", Piece::King(Colour::Black)),
+            ("a8", Piece::Rook(Colour::Black)),
+            ("h8", Piece::Rook(Colour::Black)),
+        ])
+    }
+
+    fn king_moves(game: &Game, position: &str, colour: Colour) -> Vec<String> {
+        let possible_moves = game
+            .get_possible_moves(game.board, position, colour)
+            .unwrap();
+        game.get_legal_moves(position, colour, possible_moves)
+            .unwrap()
+    }
+
+    #[test]
+    fn castle_both_sides() {
+        let game = castling_position();
+
+        let moves = king_moves(&game, "e1", Colour::White);
+        assert!(moves.contains(&"g1".to_string()));
+        assert!(moves.contains(&"c1".to_string()));
+    }
+
+    #[test]
+    fn castle_moves_rook() {
+        let mut game = castling_position();
+
+        game.make_move("e1", "g1");
+        assert_eq!(game.board[1], Some(Piece::King(Colour::White)));
+        assert_eq!(game.board[2], Some(Piece::Rook(Colour::White)));
+        assert_eq!(game.board[0], None);
+        assert_eq!(game.board[3], None);
+
+        game.make_move("e8", "c8");
+        assert_eq!(game.board[61], Some(Piece::King(Colour::Black)));
+        assert_eq!(game.board[60], Some(Piece::Rook(Colour::Black)));
+        assert_eq!(game.board[63], None);
+        assert_eq!(game.board[59], None);
+
+        assert_eq!(
+            game.get_castling_rights(Colour::White),
+            crate::CastlingRights {
+                king_side: false,
+                queen_side: false
+            }
+        );
+    }
+
+    #[test]
+    fn no_castling_after_rook_moved() {
+        let mut game = castling_position();
+
+        game.make_move("h1", "h2");
+        game.make_move("a8", "a7");
+        game.make_move("h2", "h1");
+        game.make_move("a7", "a8");
+
+        let moves = king_moves(&game, "e1", Colour::White);
+        assert!(!moves.contains(&"g1".to_string()));
+        assert!(moves.contains(&"c1".to_string()));
+
+        let moves = king_moves(&game, "e8", Colour::Black);
+        assert!(moves.contains(&"g8".to_string()));
+        assert!(!moves.contains(&"c8".to_string()));
+    }
+
+    #[test]
+    fn no_castling_through_pieces() {
+        let mut game = castling_position();
+        game.set_piece(6, Some(Piece::Knight(Colour::White))); // b1
+
+        let moves = king_moves(&game, "e1", Colour::White);
+        assert!(moves.contains(&"g1".to_string()));
+        assert!(!moves.contains(&"c1".to_string()));
+    }
+
+    #[test]
+    fn no_castling_through_or_into_check() {
+        // rook on f8 covers f1, rook on c8 covers c1
+        let mut game = castling_position();
+        game.set_piece(61, Some(Piece::Rook(Colour::Black))); // c8
+        game.set_piece(58, Some(Piece::Rook(Colour::Black))); // f8
+        game.set_piece(56, None);
+        game.set_piece(63, None);
+
+        let moves = king_moves(&game, "e1", Colour::White);
+        assert!(!moves.contains(&"g1".to_string()));
+        assert!(!moves.contains(&"c1".to_string()));
+    }
+
+    #[test]
+    fn no_castling_out_of_check() {
+        let mut game = castling_position();
+        game.set_piece(59, None);
+        game.set_piece(60, Some(Piece::King(Colour::Black))); // d8
+        game.set_piece(43, Some(Piece::Rook(Colour::Black))); // e6
+
+        let moves = king_moves(&game, "e1", Colour::White);
+        assert!(!moves.contains(&"g1".to_string()));
+        assert!(!moves.contains(&"c1".to_string()));
+    }
+
+    /// white pawn on e5 with a black pawn that just double stepped d7d5 next to it
+    fn en_passant_position() -> Game {
+        let mut game = game_with_pieces(&[
+            ("e1", Piece::King(Colour::White)),
+            ("e5", Piece::Pawn(Colour::White)),
+            ("a2", Piece::Pawn(Colour::White)),
+            ("e8", Piece::King(Colour::Black)),
+            ("d7", Piece::Pawn(Colour::Black)),
+        ]);
+        game.make_move("a2", "a3");
+        game.make_move("d7", "d5");
+        game
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        let mut game = en_passant_position();
+        assert_eq!(game.get_en_passant(), Some("d6".to_string()));
+
+        let moves = game
+            .get_possible_moves(game.board, "e5", Colour::White)
+            .unwrap();
+        assert!(moves.contains(&"d6".to_string()));
+
+        game.make_move("e5", "d6");
+        assert_eq!(game.board[44], Some(Piece::Pawn(Colour::White))); // d6
+        assert_eq!(game.board[36], None); // d5
+        assert_eq!(game.get_en_passant(), None);
+    }
+
+    #[test]
+    fn en_passant_only_right_after_double_step() {
+        let mut game = en_passant_position();
+        game.make_move("a3", "a4");
+        game.make_move("e8", "f8");
+
+        let moves = game
+            .get_possible_moves(game.board, "e5", Colour::White)
+            .unwrap();
+        assert!(!moves.contains(&"d6".to_string()));
+    }
+
+    #[test]
+    fn en_passant_discovered_check() {
+        // taking en passant would clear the fifth rank between the rook and the king
+        let mut game = en_passant_position();
+        game.set_piece(3, None);
+        game.set_piece(39, Some(Piece::King(Colour::White))); // a5
+        game.set_piece(32, Some(Piece::Rook(Colour::Black))); // h5
+
+        let possible_moves = game
+            .get_possible_moves(game.board, "e5", Colour::White)
+            .unwrap();
+        let legal_moves = game
+            .get_legal_moves("e5", Colour::White, possible_moves)
+            .unwrap();
+        assert!(!legal_moves.contains(&"d6".to_string()));
+        assert!(legal_moves.contains(&"e6".to_string()));
+    }
+
+    /// white pawn on b7 about to promote, black king boxed in on h8
+    fn promotion_position() -> Game {
+        game_with_pieces(&[
+            ("e1", Piece::King(Colour::White)),
+            ("b7", Piece::Pawn(Colour::White)),
+            ("h8", Piece::King(Colour::Black)),
+            ("h7", Piece::Pawn(Colour::Black)),
+            ("g7", Piece::Pawn(Colour::Black)),
+        ])
+    }
+
+    #[test]
+    fn promotion_requires_a_piece() {
+        let mut game = promotion_position();
+
+        game.make_move("b7", "b8");
+        assert_eq!(game.board[54], Some(Piece::Pawn(Colour::White))); // still on b7
+        assert_eq!(game.board[62], None);
+        assert_eq!(game.active_colour, Colour::White);
+    }
+
+    #[test]
+    fn under_promotion() {
+        for (choice, piece) in [
+            ("r", Piece::Rook(Colour::White)),
+            ("n", Piece::Knight(Colour::White)),
+            ("b", Piece::Bishop(Colour::White)),
+        ] {
+            let mut game = promotion_position();
+            game.make_move_with_promotion("b7", "b8", choice);
+            assert_eq!(game.board[62], Some(piece));
+        }
+    }
+
+    #[test]
+    fn promotion_to_queen_gives_checkmate() {
+        let mut game = promotion_position();
+
+        let state = game.make_move_with_promotion("b7", "b8", "q");
+        assert_eq!(state, Some(GameState::CheckMate));
+        assert_eq!(game.board[62], Some(Piece::Queen(Colour::White)));
+    }
+
+    #[test]
+    fn promotion_only_on_last_rank() {
+        let mut game = Game::new();
+
+        game.make_move_with_promotion("e2", "e4", "q");
+        assert_eq!(game.board[11], Some(Piece::Pawn(Colour::White))); // still on e2
+    }
+
+    #[test]
+    fn check_does_not_end_the_game() {
+        let mut game = game_with_pieces(&[
+            ("e1", Piece::King(Colour::White)),
+            ("a1", Piece::Rook(Colour::White)),
+            ("e8", Piece::King(Colour::Black)),
+        ]);
+
+        assert_eq!(game.make_move("a1", "a8"), Some(GameState::Check));
+        assert_eq!(game.get_game_state(), GameState::Check);
+        assert_eq!(game.active_colour, Colour::Black);
+
+        assert_eq!(game.make_move("e8", "e7"), Some(GameState::InProgress));
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut game = game_with_pieces(&[
+            ("f7", Piece::King(Colour::White)),
+            ("g1", Piece::Queen(Colour::White)),
+            ("h8", Piece::King(Colour::Black)),
+        ]);
+
+        assert_eq!(game.make_move("g1", "g6"), Some(GameState::Stalemate));
+        assert!(game.is_game_over());
+
+        // no more moves once the game is drawn
+        assert_eq!(game.make_move("h8", "h7"), None);
+    }
+
+    fn square(notation: &str) -> Square {
+        Square::parse(notation).unwrap()
+    }
+
+    #[test]
+    fn typed_legal_moves() {
+        let game = Game::new();
+        let moves = game.legal_moves();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.contains(&Move::new(square("g1"), square("f3"), None)));
+
+        let knight_moves = game.legal_moves_from(square("b1"));
+        assert_eq!(knight_moves.len(), 2);
+        assert!(game.legal_moves_from(square("e7")).is_empty());
+        assert_eq!(
+            game.get_piece(square("d1")),
+            Some(Piece::Queen(Colour::White))
+        );
+    }
+
+    #[test]
+    fn move_flags() {
+        let game = en_passant_position();
+        let moves = game.legal_moves_from(square("e5"));
+        let capture = moves.iter().find(|mv| mv.to == square("d6")).unwrap();
+        assert!(capture.capture && capture.en_passant && !capture.castle);
+        assert_eq!(capture.to_string(), "e5d6");
+
+        let game = castling_position();
+        let castle = game
+            .legal_moves_from(square("e1"))
+            .into_iter()
+            .find(|mv| mv.to == square("g1"))
+            .unwrap();
+        assert!(castle.castle && !castle.capture);
+
+        // one move for every piece a pawn can promote to
+        let game = promotion_position();
+        let promotions: Vec<Move> = game.legal_moves_from(square("b7"));
+        assert_eq!(promotions.len(), 4);
+        assert!(promotions.iter().any(|mv| mv.to_string() == "b7b8n"));
+    }
+
+    #[test]
+    fn play_typed_move() {
+        let mut game = Game::new();
+        let state = game.play(Move::new(square("e2"), square("e4"), None));
+        assert_eq!(state, Ok(GameState::InProgress));
+        assert_eq!(
+            game.get_piece(square("e4")),
+            Some(Piece::Pawn(Colour::White))
+        );
+        assert_eq!(game.get_en_passant(), Some("e3".to_string()));
+
+        // an illegal move leaves the board alone
+        let illegal = Move::new(square("e7"), square("e4"), None);
+        assert_eq!(game.play(illegal), Err(ChessError::IllegalMove(illegal)));
+        assert_eq!(
+            game.get_piece(square("e7")),
+            Some(Piece::Pawn(Colour::Black))
+        );
+        assert_eq!(game.active_colour, Colour::Black);
+
+        let mut game = promotion_position();
+        let queen = Some(Piece::Queen(Colour::White));
+        assert!(game
+            .play(Move::new(square("b7"), square("b8"), queen))
+            .is_ok());
+        assert_eq!(game.get_piece(square("b8")), queen);
+    }
+
+    #[test]
+    fn parse_coordinate_moves() {
+        let game = Game::new();
+        let mv = game.parse_move("g1f3").unwrap();
+        assert_eq!((mv.from, mv.to), (square("g1"), square("f3")));
+        assert_eq!(game.parse_move("e2e5"), None);
+        assert_eq!(game.parse_move("e2"), None);
+
+        let game = castling_position();
+        assert!(game.parse_move("e1g1").unwrap().castle);
+
+        let game = promotion_position();
+        assert_eq!(game.parse_move("b7b8"), None);
+        assert_eq!(
+            game.parse_move("b7b8n").unwrap().promotion,
+            Some(Piece::Knight(Colour::White))
+        );
+    }
+
+    #[test]
+    fn errors_instead_of_output() {
+        let mut game = Game::new();
+        assert_eq!(
+            game.try_make_move("e9", "e4"),
+            Err(ChessError::InvalidNotation("e9".to_string()))
+        );
+        assert_eq!(
+            game.try_make_move("e4", "e5"),
+            Err(ChessError::EmptySquare(square("e4")))
+        );
+        assert_eq!(
+            game.try_make_move("e7", "e5"),
+            Err(ChessError::WrongColour(square("e7")))
+        );
+        assert_eq!(
+            game.try_get_possible_moves(game.board, "x1", Colour::White),
+            Err(ChessError::InvalidNotation("x1".to_string()))
+        );
+        assert_eq!(
+            Game::try_convert_from_notation("z3"),
+            Err(ChessError::InvalidNotation("z3".to_string()))
+        );
+        assert_eq!(Game::try_convert_from_notation("e1"), Ok((3, 0)));
+
+        // the old functions still return the unchanged state for an illegal move
+        assert_eq!(game.make_move("e2", "e5"), Some(GameState::InProgress));
+        assert_eq!(game.make_move("e4", "e5"), None);
+    }
+
+    #[test]
+    fn promotion_errors() {
+        let mut game = promotion_position();
+        let push = Move::new(square("b7"), square("b8"), None);
+        assert_eq!(
+            game.try_make_move("b7", "b8"),
+            Err(ChessError::PromotionRequired(push))
+        );
+        assert_eq!(
+            game.try_make_move_with_promotion("b7", "b8", "k"),
+            Err(ChessError::InvalidPromotionPiece("k".to_string()))
+        );
+        assert_eq!(
+            game.try_make_move_with_promotion("e1", "e2", "q"),
+            Err(ChessError::InvalidPromotion(Move::new(
+                square("e1"),
+                square("e2"),
+                Some(Piece::Queen(Colour::White))
+            )))
+        );
+        assert_eq!(
+            game.try_set_promotion("e1", "q"),
+            Err(ChessError::NotAPawn(square("e1")))
+        );
+        assert_eq!(
+            game.try_set_promotion("b7", "r"),
+            Err(ChessError::NotOnLastRank(square("b7")))
+        );
+        assert_eq!(
+            game.promote(square("b7"), Piece::King(Colour::White)),
+            Err(ChessError::InvalidPromotionPiece("k".to_string()))
+        );
+
+        // a pawn put on the last rank by hand
+        game.set_piece(square("d8").index(), Some(Piece::Pawn(Colour::White)));
+        assert_eq!(game.try_set_promotion("d8", "r"), Ok(()));
+        assert_eq!(
+            game.board[square("d8").index()],
+            Some(Piece::Rook(Colour::White))
+        );
+        assert_eq!(game.hash(), game.compute_hash());
+    }
+
+    #[test]
+    fn no_moves_once_game_is_over() {
+        let mut game = Game::new();
+        for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+            assert!(game.try_make_move(from, to).is_ok());
+        }
+        assert_eq!(game.try_make_move("e2", "e4"), Err(ChessError::GameOver));
+        assert_eq!(game.try_make_san_move("e4"), Err(ChessError::GameOver));
+    }
 }
@@ -0,0 +1,7 @@
+//! Plays a game of chess in the terminal, type `help` for the commands.
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    wingmyr_chess::run_play(io::stdin().lock(), io::stdout())
+}
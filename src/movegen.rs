@@ -0,0 +1,253 @@
+//! Generating moves from the bitboards. Every piece gets a set of target squares from the attack
+//! tables, and a move is only kept if its king isn't attacked once it's made on a copy of the bitboards.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use crate::bitboard::{self, Bitboards, Positions};
+use crate::{Colour, Game, Move, Piece, Square};
+
+/// No position has more legal moves than this, the most known is 218.
+pub(crate) const MAX_MOVES: usize = 256;
+
+/// A list of moves on the stack, filled by `Game::generate_moves` without allocating.
+/// It derefs to a slice, so it can be iterated, indexed and sorted like one.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        let empty = Square::from_index(0).unwrap();
+        MoveList {
+            moves: [Move::new(empty, empty, None); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Game {
+    /// Fills `moves` with every legal move of the side to move, like `legal_moves` does,
+    /// but without allocating. Anything already in the list is replaced.
+    pub fn generate_moves(&self, moves: &mut MoveList) {
+        moves.clear();
+        let colour = self.active_colour;
+        for from in Positions(self.bitboards.colour(colour)) {
+            for to in Positions(self.legal_targets(from, colour)) {
+                self.push_moves(moves, from, to);
+            }
+        }
+    }
+
+    /// adds the move from `from` to `to`, once for every piece a pawn can promote to
+    fn push_moves(&self, moves: &mut MoveList, from: usize, to: usize) {
+        let (from, to) = (from as i32, to as i32);
+        let promoting = self.board[from as usize] == Some(Piece::Pawn(self.active_colour))
+            && (to / 8 == 0 || to / 8 == 7);
+        if promoting {
+            for piece in [
+                Piece::Queen(self.active_colour),
+                Piece::Rook(self.active_colour),
+                Piece::Bishop(self.active_colour),
+                Piece::Knight(self.active_colour),
+            ] {
+                moves.push(self.describe_move(from, to, Some(piece)));
+            }
+        } else {
+            moves.push(self.describe_move(from, to, None));
+        }
+    }
+
+    /// the squares the piece on `from` can legally move to on the current board
+    pub(crate) fn legal_targets(&self, from: usize, colour: Colour) -> u64 {
+        let mut targets = self.pseudo_targets(&self.bitboards, from, colour);
+        for to in Positions(targets) {
+            if !self.leaves_king_safe(&self.bitboards, from, to, colour) {
+                targets &= !(1 << to);
+            }
+        }
+        targets
+    }
+
+    /// the squares the piece on `from` can reach by its own movement rules, including castling,
+    /// without looking at whether the move leaves its king in check
+    pub(crate) fn pseudo_targets(&self, bitboards: &Bitboards, from: usize, colour: Colour) -> u64 {
+        let own = bitboards.colour(colour);
+        let occupied = bitboards.occupied();
+
+        let targets = match bitboards.piece_at(from) {
+            Some(Piece::Pawn(_)) => return self.pawn_targets(bitboards, from, colour),
+            Some(Piece::Knight(_)) => bitboard::knight_attacks(from),
+            Some(Piece::Bishop(_)) => bitboard::bishop_attacks(from, occupied),
+            Some(Piece::Rook(_)) => bitboard::rook_attacks(from, occupied),
+            Some(Piece::Queen(_)) => {
+                bitboard::rook_attacks(from, occupied) | bitboard::bishop_attacks(from, occupied)
+            }
+            Some(Piece::King(_)) => {
+                bitboard::king_attacks(from) | self.castling_targets(bitboards, from, colour)
+            }
+            None => 0,
+        };
+        targets & !own
+    }
+
+    fn pawn_targets(&self, bitboards: &Bitboards, from: usize, colour: Colour) -> u64 {
+        let empty = !bitboards.occupied();
+        let enemies = bitboards.occupied() & !bitboards.colour(colour);
+
+        // one step forward, or two if the pawn hasn't been moved
+        let (single, start_rank) = match colour {
+            Colour::White => ((1u64 << from) << 8, 1),
+            Colour::Black => ((1u64 << from) >> 8, 6),
+        };
+        let mut targets = single & empty;
+        if targets != 0 && from / 8 == start_rank {
+            targets |= match colour {
+                Colour::White => targets << 8,
+                Colour::Black => targets >> 8,
+            } & empty;
+        }
+
+        // the pawn that just double stepped can only be taken by the side to move
+        let en_passant = match self.en_passant {
+            Some(square) if colour == self.active_colour => 1 << square,
+            _ => 0,
+        };
+        targets | bitboard::pawn_attacks(from, colour) & (enemies | en_passant)
+    }
+
+    /// Returns the castling targets for the king on `from`, following the rules:
+    /// - neither the king nor the rook has moved
+    /// - every square between them is empty
+    /// - the king is not in check, and does not pass through or land on a threatened square
+    fn castling_targets(&self, bitboards: &Bitboards, from: usize, colour: Colour) -> u64 {
+        let (home, enemy) = match colour {
+            Colour::White => (3, Colour::Black),
+            Colour::Black => (7 * 8 + 3, Colour::White),
+        };
+        if from != home || bitboards.attacked(home, enemy) {
+            return 0;
+        }
+
+        let rights = self.get_castling_rights(colour);
+        let rooks = bitboards.pieces(Piece::Rook(colour));
+        let occupied = bitboards.occupied();
+        let mut targets = 0;
+
+        // king side, the rook stands three steps to the right (towards h)
+        if rights.king_side
+            && rooks & 1 << (home - 3) != 0
+            && occupied & (0b11 << (home - 2)) == 0
+            && !bitboards.attacked(home - 1, enemy)
+            && !bitboards.attacked(home - 2, enemy)
+        {
+            targets |= 1 << (home - 2);
+        }
+
+        // queen side, the rook stands four steps to the left (towards a)
+        if rights.queen_side
+            && rooks & 1 << (home + 4) != 0
+            && occupied & (0b111 << (home + 1)) == 0
+            && !bitboards.attacked(home + 1, enemy)
+            && !bitboards.attacked(home + 2, enemy)
+        {
+            targets |= 1 << (home + 2);
+        }
+
+        targets
+    }
+
+    /// returns true if moving from `from` to `to` doesn't leave the king in check
+    pub(crate) fn leaves_king_safe(
+        &self,
+        bitboards: &Bitboards,
+        from: usize,
+        to: usize,
+        colour: Colour,
+    ) -> bool {
+        let mut after = *bitboards;
+        let piece = after.piece_at(from);
+
+        // en passant, the captured pawn stands next to the moving pawn rather than on the target square
+        if matches!(piece, Some(Piece::Pawn(_)))
+            && self.en_passant == Some(to as i32)
+            && from % 8 != to % 8
+        {
+            after.set((from / 8) * 8 + to % 8, None);
+        }
+
+        after.set(to, piece);
+        after.set(from, None);
+        !after.in_check(colour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveList;
+    use crate::Game;
+
+    #[test]
+    fn generate_moves() {
+        let mut moves = MoveList::new();
+        let game = Game::from_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        game.generate_moves(&mut moves);
+        // five king moves, four promotions, a step forward and the en passant capture
+        assert_eq!(moves.len(), 11);
+        assert_eq!(moves.iter().filter(|mv| mv.promotion.is_some()).count(), 4);
+        assert_eq!(moves.iter().filter(|mv| mv.en_passant).count(), 1);
+        assert_eq!(moves.to_vec(), game.legal_moves());
+
+        // the list is refilled rather than added to
+        let game = Game::new();
+        game.generate_moves(&mut moves);
+        game.generate_moves(&mut moves);
+        assert_eq!(moves.len(), 20);
+    }
+
+    #[test]
+    fn most_moves() {
+        // the position with the most legal moves known
+        let game = Game::from_fen("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        game.generate_moves(&mut moves);
+        assert_eq!(moves.len(), 218);
+    }
+}
@@ -0,0 +1,63 @@
+//! A typed move, as generated by `Game::legal_moves` and played with `Game::play`.
+
+use std::fmt;
+
+use crate::{Game, Piece, Square};
+
+/// A move from one square to another.
+///
+/// The flags describe what the move does on the board it was generated for.
+/// They don't have to be set when passing a move to `Game::play`, only `from`, `to`
+/// and `promotion` are used to find the matching legal move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    /// the piece a pawn turns into when reaching the last rank
+    pub promotion: Option<Piece>,
+    /// an enemy piece is taken, including en passant
+    pub capture: bool,
+    /// the king moves two squares and the rook jumps over it
+    pub castle: bool,
+    /// a pawn takes a pawn that just moved two squares past it
+    pub en_passant: bool,
+}
+
+impl Move {
+    /// A move without any flags set.
+    pub fn new(from: Square, to: Square, promotion: Option<Piece>) -> Move {
+        Move {
+            from,
+            to,
+            promotion,
+            capture: false,
+            castle: false,
+            en_passant: false,
+        }
+    }
+}
+
+impl Game {
+    /// Finds the legal move written in coordinate notation, e.g. "e2e4", "e1g1" for castling
+    /// or "e7e8q" for a promotion.
+    pub fn parse_move(&self, notation: &str) -> Option<Move> {
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| mv.to_string().eq_ignore_ascii_case(notation))
+    }
+}
+
+/// Coordinate notation, e.g. "e2e4" or "e7e8q" for a promotion.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(Piece::Queen(_)) => write!(f, "q"),
+            Some(Piece::Rook(_)) => write!(f, "r"),
+            Some(Piece::Bishop(_)) => write!(f, "b"),
+            Some(Piece::Knight(_)) => write!(f, "n"),
+            _ => Ok(()),
+        }
+    }
+}
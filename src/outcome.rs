@@ -0,0 +1,261 @@
+//! How a game ended: resigning, agreeing to a draw, running out of time, or the position on the board.
+
+use std::fmt;
+
+use crate::{ChessError, Colour, Game, GameState};
+
+/// The way a game came to an end.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    /// a player ran out of time, this is a draw if the other side can't checkmate anymore
+    Timeout,
+    Stalemate,
+    Repetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    /// the players agreed to a draw
+    Agreement,
+}
+
+/// The result of a finished game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// `None` for a draw
+    pub winner: Option<Colour>,
+    pub termination: Termination,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.termination {
+            Termination::Checkmate => "checkmate",
+            Termination::Resignation => "resignation",
+            Termination::Timeout => "timeout",
+            Termination::Stalemate => "stalemate",
+            Termination::Repetition => "repetition",
+            Termination::FiftyMoveRule => "the fifty-move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Agreement => "agreement",
+        };
+        match self.winner {
+            Some(Colour::White) => write!(f, "white wins by {}", reason),
+            Some(Colour::Black) => write!(f, "black wins by {}", reason),
+            None => write!(f, "draw by {}", reason),
+        }
+    }
+}
+
+impl Game {
+    /// Ends the game with `colour` giving up, and returns the new state.
+    pub fn resign(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        self.end_game(Outcome {
+            winner: Some(other_colour(colour)),
+            termination: Termination::Resignation,
+        })
+    }
+
+    /// Ends the game because `colour` ran out of time, and returns the new state.
+    /// The other side wins, unless it has too little material left to ever checkmate.
+    pub fn time_out(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        let winner = other_colour(colour);
+        self.end_game(Outcome {
+            winner: if self.has_insufficient_material(winner) {
+                None
+            } else {
+                Some(winner)
+            },
+            termination: Termination::Timeout,
+        })
+    }
+
+    /// Offers a draw to the opponent of `colour`. The offer stands until it's accepted or
+    /// declined, or the opponent makes a move.
+    pub fn offer_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        if self.is_game_over() {
+            return Err(ChessError::GameOver);
+        }
+        self.draw_offer = Some(colour);
+        Ok(())
+    }
+
+    /// Accepts the draw the opponent of `colour` has offered, ending the game.
+    pub fn accept_draw(&mut self, colour: Colour) -> Result<GameState, ChessError> {
+        if self.draw_offer != Some(other_colour(colour)) {
+            return Err(ChessError::NoDrawOffer);
+        }
+        self.end_game(Outcome {
+            winner: None,
+            termination: Termination::Agreement,
+        })
+    }
+
+    /// Turns down the draw the opponent of `colour` has offered.
+    pub fn decline_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        if self.draw_offer != Some(other_colour(colour)) {
+            return Err(ChessError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    /// Returns the colour that offered a draw, if the offer still stands.
+    pub fn get_draw_offer(&self) -> Option<Colour> {
+        self.draw_offer
+    }
+
+    /// Returns who won and why once the game is over, `None` while it's still going.
+    pub fn get_outcome(&self) -> Option<Outcome> {
+        if self.outcome.is_some() {
+            return self.outcome;
+        }
+
+        let draw = |termination| {
+            Some(Outcome {
+                winner: None,
+                termination,
+            })
+        };
+        match self.state {
+            GameState::CheckMate => Some(Outcome {
+                winner: Some(other_colour(self.active_colour)),
+                termination: Termination::Checkmate,
+            }),
+            GameState::Stalemate => draw(Termination::Stalemate),
+            GameState::Repetition => draw(Termination::Repetition),
+            GameState::FiftyMoveRule => draw(Termination::FiftyMoveRule),
+            GameState::InsufficientMaterial => draw(Termination::InsufficientMaterial),
+            _ => None,
+        }
+    }
+
+    /// ends the game for a reason that doesn't come from the position on the board
+    fn end_game(&mut self, outcome: Outcome) -> Result<GameState, ChessError> {
+        if self.is_game_over() {
+            return Err(ChessError::GameOver);
+        }
+
+        self.state = match outcome.winner {
+            Some(_) => GameState::GameOver,
+            None => GameState::Draw,
+        };
+        self.outcome = Some(outcome);
+        self.draw_offer = None;
+        self.stop_clock();
+        Ok(self.state)
+    }
+}
+
+fn other_colour(colour: Colour) -> Colour {
+    match colour {
+        Colour::White => Colour::Black,
+        Colour::Black => Colour::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, Termination};
+    use crate::{ChessError, Colour, Game, GameState};
+
+    #[test]
+    fn resignation() {
+        let mut game = Game::new();
+        game.make_san_move("e4");
+        assert_eq!(game.get_outcome(), None);
+
+        assert_eq!(game.resign(Colour::Black), Ok(GameState::GameOver));
+        assert!(game.is_game_over());
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome {
+                winner: Some(Colour::White),
+                termination: Termination::Resignation,
+            })
+        );
+        assert_eq!(game.resign(Colour::White), Err(ChessError::GameOver));
+        assert_eq!(game.make_san_move("e5"), None);
+        assert!(game.to_pgn(&[]).ends_with("1. e4 1-0\n"));
+    }
+
+    #[test]
+    fn draw_offers() {
+        let mut game = Game::new();
+        assert_eq!(
+            game.accept_draw(Colour::Black),
+            Err(ChessError::NoDrawOffer)
+        );
+
+        // declined
+        game.offer_draw(Colour::White).unwrap();
+        assert_eq!(game.get_draw_offer(), Some(Colour::White));
+        assert_eq!(game.decline_draw(Colour::Black), Ok(()));
+        assert_eq!(game.get_draw_offer(), None);
+
+        // the offer stands through the offering side's own move, but lapses once the opponent moves
+        game.offer_draw(Colour::White).unwrap();
+        game.make_san_move("e4");
+        assert_eq!(game.get_draw_offer(), Some(Colour::White));
+        game.make_san_move("e5");
+        assert_eq!(game.get_draw_offer(), None);
+
+        // only the opponent can answer an offer
+        game.offer_draw(Colour::White).unwrap();
+        assert_eq!(
+            game.accept_draw(Colour::White),
+            Err(ChessError::NoDrawOffer)
+        );
+        assert_eq!(
+            game.decline_draw(Colour::White),
+            Err(ChessError::NoDrawOffer)
+        );
+        assert_eq!(game.get_draw_offer(), Some(Colour::White));
+
+        // accepted
+        assert_eq!(game.accept_draw(Colour::Black), Ok(GameState::Draw));
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome {
+                winner: None,
+                termination: Termination::Agreement,
+            })
+        );
+        assert_eq!(game.offer_draw(Colour::Black), Err(ChessError::GameOver));
+    }
+
+    #[test]
+    fn timeout() {
+        let mut game = Game::new();
+        game.time_out(Colour::White).unwrap();
+        assert_eq!(
+            game.get_outcome().unwrap().to_string(),
+            "black wins by timeout"
+        );
+
+        // a lone king can't win on time
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(game.time_out(Colour::White), Ok(GameState::Draw));
+        assert_eq!(game.get_outcome().unwrap().to_string(), "draw by timeout");
+    }
+
+    #[test]
+    fn outcome_from_the_board() {
+        let mut game = Game::new();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san);
+        }
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome {
+                winner: Some(Colour::Black),
+                termination: Termination::Checkmate,
+            })
+        );
+
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.get_outcome().unwrap().to_string(), "draw by stalemate");
+    }
+}
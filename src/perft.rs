@@ -0,0 +1,132 @@
+//! Counting the positions reachable in a number of moves, to check the move generator against
+//! numbers that are known to be right.
+
+use crate::{Game, Move, MoveList};
+
+impl Game {
+    /// Returns the number of move sequences of `depth` half moves that can be played from here.
+    /// Every legal move is followed, even when the game would have ended in a draw by the rules.
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        self.clone().count_leaves(depth)
+    }
+
+    /// Same as `perft`, but split up by the first move, which helps to find where two move
+    /// generators disagree.
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut game = self.clone();
+        game.legal_moves()
+            .into_iter()
+            .map(|mv| {
+                game.apply_move(mv);
+                let count = match depth {
+                    0 | 1 => 1,
+                    _ => game.count_leaves(depth - 1),
+                };
+                game.take_back();
+                (mv, count)
+            })
+            .collect()
+    }
+
+    fn count_leaves(&mut self, depth: u32) -> u64 {
+        let mut moves = MoveList::new();
+        self.generate_moves(&mut moves);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut count = 0;
+        for &mv in moves.iter() {
+            self.apply_move(mv);
+            count += self.count_leaves(depth - 1);
+            self.take_back();
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Game;
+
+    /// checks the perft numbers of a position for depths 1, 2, ...
+    fn check_perft(fen: &str, counts: &[u64]) {
+        let game = Game::from_fen(fen).unwrap();
+        for (depth, count) in counts.iter().enumerate() {
+            assert_eq!(
+                game.perft(depth as u32 + 1),
+                *count,
+                "{} at depth {}",
+                fen,
+                depth + 1
+            );
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        check_perft(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281],
+        );
+    }
+
+    #[test]
+    fn kiwipete() {
+        check_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn position_3() {
+        check_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        );
+    }
+
+    #[test]
+    fn position_4() {
+        check_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+        // the same position with the colours swapped
+        check_perft(
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            &[6, 264, 9467],
+        );
+    }
+
+    #[test]
+    fn position_5() {
+        check_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    #[test]
+    fn position_6() {
+        check_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+
+    #[test]
+    fn divide_adds_up() {
+        let game = Game::new();
+        let divide = game.divide(3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), 8902);
+        assert!(divide
+            .iter()
+            .any(|(mv, count)| mv.to_string() == "e2e4" && *count == 600));
+    }
+}
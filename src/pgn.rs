@@ -0,0 +1,622 @@
+//! Reading and writing games in Portable Game Notation (PGN).
+//!
+//! ```text
+//! [Event "Casual game"]
+//! [White "Anderssen"]
+//! [Black "Kieseritzky"]
+//! [Result "1-0"]
+//!
+//! 1. e4 e5 2. f4 {King's Gambit} exf4 (2... d5) 3. Bc4 $1 Qh4+ 1-0
+//! ```
+//!
+//! Every move is checked against the legal moves of the position (`legal_targets` on the
+//! bitboards) while reading, so a parsed game can always be replayed.
+
+use std::fmt;
+
+use crate::san::{move_to_san, san_to_move, with_suffix};
+use crate::{Colour, FenError, Game, Move};
+
+/// A game read from PGN.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnGame {
+    /// tag pairs in the order they appear, e.g. `("White", "Anderssen")`
+    pub tags: Vec<(String, String)>,
+    /// comments in front of the first move
+    pub comments: Vec<String>,
+    /// the main line
+    pub moves: Vec<PgnMove>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*` for a game that isn't finished
+    pub result: String,
+}
+
+/// A single move of a `PgnGame`, together with its annotations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnMove {
+    /// the move as it was written, without `!` and `?` annotations
+    pub san: String,
+    /// the move itself, with its flags as they were in the position it was played in
+    pub mv: Move,
+    /// numeric annotation glyphs, `!`, `?`, `!!`, `??`, `!?` and `?!` are stored as 1 to 6
+    pub nags: Vec<u8>,
+    /// comments following the move
+    pub comments: Vec<String>,
+    /// alternatives to this move, each one starting from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+/// Describes what is wrong with a PGN text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PgnError {
+    /// a tag pair that isn't written as `[Name "value"]`
+    InvalidTag(String),
+    /// the FEN tag holds a position that can't be read
+    InvalidFen(FenError),
+    /// the move at the given ply, counted from 1 at the start of the game, can't be played
+    IllegalMove {
+        ply: usize,
+        san: String,
+    },
+    /// something that has no meaning at its place in the text
+    UnexpectedToken(String),
+    UnterminatedComment,
+    UnterminatedString,
+    UnterminatedVariation,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair near '{}'", tag),
+            PgnError::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnError::IllegalMove { ply, san } => {
+                write!(f, "illegal move '{}' at ply {}", san, ply)
+            }
+            PgnError::UnexpectedToken(token) => write!(f, "unexpected '{}'", token),
+            PgnError::UnterminatedComment => write!(f, "comment is never closed"),
+            PgnError::UnterminatedString => write!(f, "tag value is never closed"),
+            PgnError::UnterminatedVariation => write!(f, "variation is never closed"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl PgnGame {
+    /// Reads every game in a PGN text.
+    pub fn parse(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            index: 0,
+        };
+
+        let mut games = vec![];
+        while parser.peek().is_some() {
+            games.push(parser.parse_game()?);
+        }
+        Ok(games)
+    }
+
+    /// Returns the value of a tag, e.g. `tag("White")`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the starting position followed by the position after every move of the main line.
+    pub fn positions(&self) -> Result<Vec<Game>, PgnError> {
+        let mut game = match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => Game::new(),
+        };
+
+        let mut positions = vec![game.clone()];
+        for (index, pgn_move) in self.moves.iter().enumerate() {
+            if game.play(pgn_move.mv).is_err() {
+                return Err(PgnError::IllegalMove {
+                    ply: index + 1,
+                    san: pgn_move.san.clone(),
+                });
+            }
+            positions.push(game.clone());
+        }
+        Ok(positions)
+    }
+}
+
+impl Game {
+    /// Writes the game played so far as PGN.
+    /// The Seven Tag Roster is always included, with "?" for any of them missing from `tags`.
+    pub fn to_pgn(&self, tags: &[(&str, &str)]) -> String {
+        let result = tags
+            .iter()
+            .find(|(name, _)| *name == "Result")
+            .map(|(_, value)| *value)
+            .unwrap_or(self.result());
+
+        let mut all_tags: Vec<(&str, &str)> = vec![];
+        for (name, default) in [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+        ] {
+            let value = tags
+                .iter()
+                .find(|(tag, _)| *tag == name)
+                .map(|(_, value)| *value)
+                .unwrap_or(default);
+            all_tags.push((name, value));
+        }
+        if let Some(fen) = &self.start_fen {
+            all_tags.push(("SetUp", "1"));
+            all_tags.push(("FEN", fen));
+        }
+        for (name, value) in tags {
+            if !all_tags.iter().any(|(tag, _)| tag == name) {
+                all_tags.push((name, value));
+            }
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in all_tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn += &format!("[{} \"{}\"]\n", name, value);
+        }
+        pgn += "\n";
+
+        // replay the game to write every move in the position it was made in
+        let mut game = self.starting_position();
+        let mut tokens = vec![];
+        for (index, entry) in self.history.iter().enumerate() {
+            match game.active_colour {
+                Colour::White => tokens.push(format!("{}.", game.fullmove_number)),
+                Colour::Black if index == 0 => tokens.push(format!("{}...", game.fullmove_number)),
+                Colour::Black => (),
+            }
+
+            let san = move_to_san(&game, entry.mv);
+            // the history only holds legal moves, so there is nothing to check again
+            game.apply_move(entry.mv);
+            tokens.push(with_suffix(san, &game));
+        }
+        tokens.push(result.to_string());
+
+        // export format keeps lines below 80 characters
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() >= 80 {
+                pgn += &line;
+                pgn += "\n";
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &token;
+        }
+        pgn += &line;
+        pgn += "\n";
+
+        pgn
+    }
+
+    /// the game as it was before the first move
+    pub(crate) fn starting_position(&self) -> Game {
+        match &self.start_fen {
+            Some(fen) => Game::from_fen(fen).expect("the start position is written by to_fen"),
+            None => Game::new(),
+        }
+    }
+
+    /// the PGN result of the game, `*` while it's still going
+    fn result(&self) -> &'static str {
+        match self.get_outcome() {
+            Some(outcome) => match outcome.winner {
+                Some(Colour::White) => "1-0",
+                Some(Colour::Black) => "0-1",
+                None => "1/2-1/2",
+            },
+            None => "*",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    Str(String),
+    Comment(String),
+    Nag(u8),
+    /// moves, move numbers, tag names and results
+    Symbol(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+
+        match c {
+            c if c.is_whitespace() => (),
+            // escaped lines and rest of line comments
+            '%' | ';' if c == ';' || at_line_start => {
+                let mut comment = String::new();
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                if c == ';' {
+                    tokens.push(Token::Comment(comment.trim().to_string()));
+                }
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(PgnError::UnterminatedComment),
+                    }
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => value.push(c),
+                            None => return Err(PgnError::UnterminatedString),
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(PgnError::UnterminatedString),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '[' => tokens.push(Token::OpenBracket),
+            ']' => tokens.push(Token::CloseBracket),
+            '(' => tokens.push(Token::OpenParen),
+            ')' => tokens.push(Token::CloseParen),
+            // periods only follow move numbers
+            '.' => (),
+            '*' => tokens.push(Token::Symbol("*".to_string())),
+            '$' => {
+                let mut number = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+                match number.parse::<u8>() {
+                    Ok(nag) => tokens.push(Token::Nag(nag)),
+                    Err(_) => return Err(PgnError::UnexpectedToken(format!("${}", number))),
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '!' || c == '?' => {
+                let mut symbol = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || "_+#=:-/!?".contains(*c))
+                {
+                    symbol.push(c);
+                }
+                tokens.push(Token::Symbol(symbol));
+            }
+            other => return Err(PgnError::UnexpectedToken(other.to_string())),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_game(&mut self) -> Result<PgnGame, PgnError> {
+        let mut tags = vec![];
+        while self.peek() == Some(&Token::OpenBracket) {
+            self.next();
+            let name = match self.next() {
+                Some(Token::Symbol(name)) => name,
+                other => return Err(PgnError::InvalidTag(format!("{:?}", other))),
+            };
+            let value = match self.next() {
+                Some(Token::Str(value)) => value,
+                _ => return Err(PgnError::InvalidTag(name)),
+            };
+            if self.next() != Some(Token::CloseBracket) {
+                return Err(PgnError::InvalidTag(name));
+            }
+            tags.push((name, value));
+        }
+
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Game::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => Game::new(),
+        };
+
+        let mut comments = vec![];
+        let moves = self.parse_line(&start, 1, &mut comments)?;
+
+        let result = match self.peek() {
+            Some(Token::Symbol(result)) if is_result(result) => {
+                let result = result.clone();
+                self.next();
+                result
+            }
+            Some(Token::CloseParen) => return Err(PgnError::UnexpectedToken(")".to_string())),
+            // the next game starts or the text ends without a result
+            _ => "*".to_string(),
+        };
+
+        Ok(PgnGame {
+            tags,
+            comments,
+            moves,
+            result,
+        })
+    }
+
+    /// reads moves starting from `start` until the line ends, `first_ply` is the ply of the first move
+    fn parse_line(
+        &mut self,
+        start: &Game,
+        first_ply: usize,
+        leading_comments: &mut Vec<String>,
+    ) -> Result<Vec<PgnMove>, PgnError> {
+        let mut game = start.clone();
+        let mut before_last_move: Option<Game> = None;
+        let mut moves: Vec<PgnMove> = vec![];
+
+        loop {
+            match self.peek() {
+                None | Some(Token::CloseParen) | Some(Token::OpenBracket) => break,
+                Some(Token::Symbol(symbol)) if is_result(symbol) => break,
+                _ => (),
+            }
+
+            match self.next() {
+                Some(Token::Comment(comment)) => match moves.last_mut() {
+                    Some(last) => last.comments.push(comment),
+                    None => leading_comments.push(comment),
+                },
+                Some(Token::Nag(nag)) => match moves.last_mut() {
+                    Some(last) => last.nags.push(nag),
+                    None => return Err(PgnError::UnexpectedToken(format!("${}", nag))),
+                },
+                Some(Token::OpenParen) => {
+                    let previous = match &before_last_move {
+                        Some(previous) => previous.clone(),
+                        None => return Err(PgnError::UnexpectedToken("(".to_string())),
+                    };
+
+                    let mut variation_comments = vec![];
+                    let mut variation = self.parse_line(
+                        &previous,
+                        first_ply + moves.len() - 1,
+                        &mut variation_comments,
+                    )?;
+                    if self.next() != Some(Token::CloseParen) {
+                        return Err(PgnError::UnterminatedVariation);
+                    }
+
+                    // comments in front of the variation's first move are kept with that move
+                    if let Some(first) = variation.first_mut() {
+                        variation_comments.append(&mut first.comments);
+                        first.comments = variation_comments;
+                    }
+                    if let Some(last) = moves.last_mut() {
+                        last.variations.push(variation);
+                    }
+                }
+                // move numbers
+                Some(Token::Symbol(symbol)) if symbol.chars().all(|c| c.is_ascii_digit()) => (),
+                Some(Token::Symbol(symbol)) => {
+                    let san = symbol.trim_end_matches(['!', '?']);
+                    let nag = match &symbol[san.len()..] {
+                        "" => None,
+                        "!" => Some(1),
+                        "?" => Some(2),
+                        "!!" => Some(3),
+                        "??" => Some(4),
+                        "!?" => Some(5),
+                        "?!" => Some(6),
+                        _ => return Err(PgnError::UnexpectedToken(symbol)),
+                    };
+
+                    // an annotation written apart from its move
+                    if san.is_empty() {
+                        match (moves.last_mut(), nag) {
+                            (Some(last), Some(nag)) => last.nags.push(nag),
+                            _ => return Err(PgnError::UnexpectedToken(symbol)),
+                        }
+                        continue;
+                    }
+
+                    let illegal = PgnError::IllegalMove {
+                        ply: first_ply + moves.len(),
+                        san: san.to_string(),
+                    };
+                    let mv = san_to_move(&game, san).ok_or(illegal.clone())?;
+
+                    before_last_move = Some(game.clone());
+                    if game.play(mv).is_err() {
+                        return Err(illegal);
+                    }
+
+                    moves.push(PgnMove {
+                        san: san.to_string(),
+                        mv,
+                        nags: nag.into_iter().collect(),
+                        comments: vec![],
+                        variations: vec![],
+                    });
+                }
+                Some(token) => return Err(PgnError::UnexpectedToken(format!("{:?}", token))),
+                None => break,
+            }
+        }
+
+        Ok(moves)
+    }
+}
+
+fn is_result(symbol: &str) -> bool {
+    matches!(symbol, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PgnError, PgnGame};
+    use crate::{Colour, Game, GameState, Piece};
+
+    const SCHOLARS_MATE: &str = r#"[Event "Casual game"]
+[Site "?"]
+[White "Teacher"]
+[Black "Student"]
+[Result "1-0"]
+
+1. e4 e5 2. Bc4 {aiming at f7} Nc6 (2... Nf6 3. d3) 3. Qh5 Nf6?? $4
+4. Qxf7# 1-0
+"#;
+
+    #[test]
+    fn parse_game() {
+        let games = PgnGame::parse(SCHOLARS_MATE).unwrap();
+        assert_eq!(games.len(), 1);
+
+        let game = &games[0];
+        assert_eq!(game.tag("White"), Some("Teacher"));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.moves.len(), 7);
+
+        assert_eq!(game.moves[2].comments, vec!["aiming at f7".to_string()]);
+        assert_eq!(game.moves[3].variations.len(), 1);
+        assert_eq!(game.moves[3].variations[0][1].san, "d3");
+        assert_eq!(game.moves[5].nags, vec![4, 4]);
+
+        let positions = game.positions().unwrap();
+        assert_eq!(positions.len(), 8);
+        assert_eq!(positions[7].get_game_state(), GameState::CheckMate);
+    }
+
+    #[test]
+    fn illegal_move_reports_ply() {
+        let error = PgnGame::parse("1. e4 e5 2. Nf3 Ke6 *").unwrap_err();
+        assert_eq!(
+            error,
+            PgnError::IllegalMove {
+                ply: 4,
+                san: "Ke6".to_string()
+            }
+        );
+
+        // plies inside a variation count from the start of the game as well
+        let error = PgnGame::parse("1. e4 e5 (1... Qh4) *").unwrap_err();
+        assert_eq!(
+            error,
+            PgnError::IllegalMove {
+                ply: 2,
+                san: "Qh4".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_pgn() {
+        assert_eq!(
+            PgnGame::parse("1. e4 {never closed").unwrap_err(),
+            PgnError::UnterminatedComment
+        );
+        assert_eq!(
+            PgnGame::parse("1. e4 e5 (1... d5 *").unwrap_err(),
+            PgnError::UnterminatedVariation
+        );
+        assert_eq!(
+            PgnGame::parse("[White Teacher]").unwrap_err(),
+            PgnError::InvalidTag("White".to_string())
+        );
+    }
+
+    #[test]
+    fn several_games() {
+        let games = PgnGame::parse("1. d4 d5 1/2-1/2\n\n[Event \"Second\"]\n1. c4 *").unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result, "1/2-1/2");
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].moves[0].san, "c4");
+    }
+
+    #[test]
+    fn write_game() {
+        let mut game = Game::new();
+        for (from, to) in [
+            ("e2", "e4"),
+            ("e7", "e5"),
+            ("f1", "c4"),
+            ("b8", "c6"),
+            ("d1", "h5"),
+            ("g8", "f6"),
+            ("h5", "f7"),
+        ] {
+            game.make_move(from, to);
+        }
+
+        let pgn = game.to_pgn(&[("White", "Teacher"), ("Annotator", "Me")]);
+        assert_eq!(
+            pgn,
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"Teacher\"]\n[Black \"?\"]\n[Result \"1-0\"]\n[Annotator \"Me\"]\n\n\
+             1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"
+        );
+
+        // and it reads back the same
+        let games = PgnGame::parse(&pgn).unwrap();
+        assert_eq!(games[0].moves.len(), 7);
+        assert_eq!(games[0].tag("Annotator"), Some("Me"));
+    }
+
+    #[test]
+    fn write_game_from_position() {
+        let mut game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 b - - 0 40").unwrap();
+        game.make_move("e8", "d7");
+        game.make_move_with_promotion("b7", "b8", "n");
+
+        let pgn = game.to_pgn(&[]);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/1P6/8/8/8/8/8/4K3 b - - 0 40\"]\n"));
+        // a knight alone can't checkmate, so the game is drawn
+        assert!(pgn.ends_with("40... Kd7 41. b8=N+ 1/2-1/2\n"));
+
+        let games = PgnGame::parse(&pgn).unwrap();
+        let positions = games[0].positions().unwrap();
+        assert_eq!(positions[2].board[62], Some(Piece::Knight(Colour::White)));
+    }
+}
@@ -0,0 +1,384 @@
+//! Playing a game in the terminal: the board is shown after every move, moves are typed in
+//! coordinate notation or SAN, and the built-in engine can take over either side.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use crate::{Colour, Engine, Game, GameState, Glyphs, RenderOptions, SearchLimits};
+
+const HELP: &str = "\
+commands:
+  <move>                 play a move, like e2e4, e7e8q, Nf3 or O-O
+  moves [square]         list the legal moves, or those of the piece on a square
+  undo                   take back the last move, and the engine's reply
+  fen [fen]              show the position as FEN, or set one up
+  save <file>            write the position to a file as FEN
+  load <file>            set up the position from a FEN file
+  pgn [file]             show the game as PGN, or write it to a file
+  engine white|black|off [seconds]
+                         let the engine play a side, thinking this long per move
+  board                  show the board again
+  flip                   turn the board around
+  colours                switch coloured squares on or off
+  new                    start a new game
+  help                   show this
+  quit                   leave";
+
+/// Runs an interactive game that reads commands from `input` until `quit` or the end of the
+/// input, and writes the board and replies to `output`.
+pub fn run_play(input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut play = Play::new(output);
+    play.show()?;
+    for line in input.lines() {
+        if !play.handle(&line?)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Play<W> {
+    output: W,
+    game: Game,
+    engine: Engine,
+    /// the side the engine plays, if any
+    engine_colour: Option<Colour>,
+    engine_limits: SearchLimits,
+    render: RenderOptions,
+}
+
+impl<W: Write> Play<W> {
+    fn new(output: W) -> Play<W> {
+        Play {
+            output,
+            game: Game::new(),
+            engine: Engine::new(),
+            engine_colour: None,
+            engine_limits: SearchLimits::time(Duration::from_secs(1)),
+            render: RenderOptions {
+                glyphs: Glyphs::Ascii,
+                highlight_last_move: true,
+                ..RenderOptions::default()
+            },
+        }
+    }
+
+    /// handles a line of input, and returns false once the player wants to leave
+    fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let line = line.trim();
+        let (command, argument) = line
+            .split_once(' ')
+            .map_or((line, ""), |(command, argument)| (command, argument.trim()));
+
+        match command {
+            "" => return self.prompt().map(|_| true),
+            "quit" | "exit" => return Ok(false),
+            "help" => writeln!(self.output, "{}", HELP)?,
+            "board" => return self.show().map(|_| true),
+            "flip" => {
+                self.render.perspective = match self.render.perspective {
+                    Colour::White => Colour::Black,
+                    Colour::Black => Colour::White,
+                };
+                return self.show().map(|_| true);
+            }
+            "colours" => {
+                self.render.colours = !self.render.colours;
+                return self.show().map(|_| true);
+            }
+            "new" => {
+                self.game = Game::new();
+                self.engine.clear();
+                return self.show_and_reply().map(|_| true);
+            }
+            "moves" => self.list_moves(argument)?,
+            "undo" => {
+                if self.game.undo_move().is_none() {
+                    writeln!(self.output, "no move to take back")?;
+                } else {
+                    // back to a position where it's the player's turn
+                    if self.engine_colour == Some(self.game.active_colour) {
+                        self.game.undo_move();
+                    }
+                    return self.show().map(|_| true);
+                }
+            }
+            "fen" if argument.is_empty() => writeln!(self.output, "{}", self.game.to_fen())?,
+            "fen" => return self.set_up(argument).map(|_| true),
+            "save" => match fs::write(argument, self.game.to_fen() + "\n") {
+                Ok(()) => writeln!(self.output, "saved the position to {}", argument)?,
+                Err(error) => writeln!(self.output, "can't write {}: {}", argument, error)?,
+            },
+            "load" => match fs::read_to_string(argument) {
+                Ok(fen) => return self.set_up(fen.trim()).map(|_| true),
+                Err(error) => writeln!(self.output, "can't read {}: {}", argument, error)?,
+            },
+            "pgn" => {
+                let pgn = self.game.to_pgn(&[("Event", "Terminal game")]);
+                if argument.is_empty() {
+                    write!(self.output, "{}", pgn)?;
+                } else {
+                    match fs::write(argument, pgn) {
+                        Ok(()) => writeln!(self.output, "saved the game to {}", argument)?,
+                        Err(error) => writeln!(self.output, "can't write {}: {}", argument, error)?,
+                    }
+                }
+            }
+            "engine" => return self.set_engine(argument).map(|_| true),
+            _ => return self.player_move(line).map(|_| true),
+        }
+        self.prompt()?;
+        Ok(true)
+    }
+
+    /// plays a move in coordinate notation or SAN, and lets the engine answer
+    fn player_move(&mut self, notation: &str) -> io::Result<()> {
+        let mv = self
+            .game
+            .parse_move(notation)
+            .or_else(|| self.game.parse_san(notation));
+        let result = match mv {
+            Some(mv) => self.game.play(mv).map(|_| ()),
+            None => {
+                writeln!(
+                    self.output,
+                    "'{}' is not a legal move or command, type help for a list",
+                    notation
+                )?;
+                return self.prompt();
+            }
+        };
+        match result {
+            Ok(()) => self.show_and_reply(),
+            Err(error) => {
+                writeln!(self.output, "{}", error)?;
+                self.prompt()
+            }
+        }
+    }
+
+    /// lists every legal move in SAN, or the squares the piece on `square` can move to
+    fn list_moves(&mut self, square: &str) -> io::Result<()> {
+        if square.is_empty() {
+            return writeln!(self.output, "{}", self.game.get_legal_moves_san().join(" "));
+        }
+
+        let colour = self.game.active_colour;
+        let moves = self
+            .game
+            .try_get_possible_moves(self.game.board, square, colour)
+            .and_then(|possible| self.game.try_get_legal_moves(square, colour, possible));
+        match moves {
+            Ok(moves) if moves.is_empty() => writeln!(self.output, "{} can't move", square),
+            Ok(moves) => writeln!(self.output, "{}", moves.join(" ")),
+            Err(error) => writeln!(self.output, "{}", error),
+        }
+    }
+
+    fn set_up(&mut self, fen: &str) -> io::Result<()> {
+        match Game::from_fen(fen) {
+            Ok(game) => {
+                self.game = game;
+                self.show_and_reply()
+            }
+            Err(error) => {
+                writeln!(self.output, "invalid FEN: {}", error)?;
+                self.prompt()
+            }
+        }
+    }
+
+    /// `engine white|black|off [seconds]`
+    fn set_engine(&mut self, argument: &str) -> io::Result<()> {
+        let (side, seconds) = argument.split_once(' ').unwrap_or((argument, ""));
+        if !seconds.is_empty() {
+            let time = seconds
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|&seconds| seconds > 0.0);
+            match time.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()) {
+                Some(time) => self.engine_limits = SearchLimits::time(time),
+                None => {
+                    writeln!(self.output, "'{}' is not a number of seconds", seconds)?;
+                    return self.prompt();
+                }
+            }
+        }
+
+        self.engine_colour = match side {
+            "white" => Some(Colour::White),
+            "black" => Some(Colour::Black),
+            "off" => None,
+            _ => {
+                writeln!(self.output, "the engine plays white, black or off")?;
+                return self.prompt();
+            }
+        };
+        self.reply()?;
+        self.prompt()
+    }
+
+    /// shows the new position, and the engine's reply if it's its turn
+    fn show_and_reply(&mut self) -> io::Result<()> {
+        if self.engine_colour == Some(self.game.active_colour) && !self.game.is_game_over() {
+            self.reply()?;
+            self.prompt()
+        } else {
+            self.show()
+        }
+    }
+
+    /// lets the engine move if it's its turn, and shows the board
+    fn reply(&mut self) -> io::Result<()> {
+        if self.engine_colour != Some(self.game.active_colour) || self.game.is_game_over() {
+            return Ok(());
+        }
+        let result = self.engine.search(&self.game, self.engine_limits);
+        if let Some(mv) = result.best_move {
+            let san = self.game.san(mv).unwrap_or_else(|| mv.to_string());
+            self.game
+                .play(mv)
+                .expect("the engine found an illegal move");
+            writeln!(self.output, "the engine plays {} ({})", san, result.score)?;
+        }
+        self.draw()
+    }
+
+    fn show(&mut self) -> io::Result<()> {
+        self.draw()?;
+        self.prompt()
+    }
+
+    /// the board with the last move marked, and whose turn it is or how the game ended
+    fn draw(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n{}", self.game.render(&self.render))?;
+
+        let colour = match self.game.active_colour {
+            Colour::White => "white",
+            Colour::Black => "black",
+        };
+        match self.game.get_outcome() {
+            Some(outcome) => writeln!(self.output, "game over, {}", outcome),
+            None if self.game.get_game_state() == GameState::Check => {
+                writeln!(self.output, "{} to move, in check", colour)
+            }
+            None => writeln!(self.output, "{} to move", colour),
+        }
+    }
+
+    fn prompt(&mut self) -> io::Result<()> {
+        write!(self.output, "> ")?;
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{run_play, Play};
+    use crate::{Colour, Game};
+
+    /// runs a script of commands and returns everything written
+    fn run(script: &str) -> String {
+        let mut output = Vec::new();
+        run_play(script.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// feeds commands to a game and returns it, to look at the position
+    fn session(script: &str) -> Play<Vec<u8>> {
+        let mut play = Play::new(Vec::new());
+        for line in script.lines() {
+            assert!(play.handle(line).unwrap());
+        }
+        play
+    }
+
+    #[test]
+    fn shows_the_board() {
+        let text = run("quit\n");
+        assert!(text.contains("8  r  n  b  q  k  b  n  r\n"));
+        assert!(text.contains("1  R  N  B  Q  K  B  N  R\n"));
+        assert!(text.contains("   a  b  c  d  e  f  g  h\n"));
+        assert!(text.ends_with("white to move\n> "));
+
+        let text = run("e2e4\nflip\n");
+        assert!(text.contains("4  .  .  . [P] .  .  .  .\n"));
+        assert!(text.contains("8  r  n  b  k  q  b  n  r\n"));
+        assert!(run("colours\n").contains("\x1b[0m"));
+    }
+
+    #[test]
+    fn coordinate_and_san_moves() {
+        let play = session("e2e4\nNf6\ne4e5\nd7d5\nexd6");
+        assert_eq!(play.game.get_move_history().len(), 5);
+        assert!(play.game.get_move_history()[4].en_passant);
+
+        let text = run("e2e5\nhello\n");
+        assert!(text.contains("'e2e5' is not a legal move"));
+        assert!(text.contains("'hello' is not a legal move or command"));
+    }
+
+    #[test]
+    fn lists_moves() {
+        let text = run("moves e2\nmoves e7\nmoves e1\nmoves\n");
+        assert!(text.contains("> e3 e4\n"));
+        assert!(text.contains("the piece on e7 is not yours"));
+        assert!(text.contains("e1 can't move"));
+        assert!(text.contains("> Nh3 Nf3 Nc3 Na3 h3 h4"));
+    }
+
+    #[test]
+    fn undo_and_new() {
+        let play = session("e2e4\ne7e5\nundo");
+        assert_eq!(play.game.get_move_history().len(), 1);
+
+        let play = session("e2e4\nnew");
+        assert_eq!(play.game.to_fen(), Game::new().to_fen());
+
+        assert!(run("undo\n").contains("no move to take back"));
+    }
+
+    #[test]
+    fn fen_and_pgn() {
+        let fen = "6k1/5ppp/8/8/8/8/8/3RK3 w - - 0 1";
+        let text = run(&format!("fen {}\nfen\nRd8\n", fen));
+        assert!(text.contains(&format!("> {}\n", fen)));
+        assert!(text.contains("game over, white wins by checkmate"));
+
+        let text = run("e4\ne5\npgn\nfen nonsense\n");
+        assert!(text.contains("[Event \"Terminal game\"]"));
+        assert!(text.contains("1. e4 e5"));
+        assert!(text.contains("invalid FEN"));
+
+        let path = std::env::temp_dir().join("wingmyr-chess-play-test.fen");
+        let path = path.to_str().unwrap();
+        session(&format!("e2e4\nsave {}", path));
+        let play = session(&format!("load {}", path));
+        assert_eq!(play.game.get_piece_colour(27), Some(Colour::White));
+        fs::remove_file(path).unwrap();
+        assert!(run(&format!("load {}\n", path)).contains("can't read"));
+    }
+
+    #[test]
+    fn plays_against_the_engine() {
+        let play = session("engine black 0.05\ne2e4");
+        assert_eq!(play.game.get_move_history().len(), 2);
+        let text = String::from_utf8(play.output).unwrap();
+        assert!(text.contains("the engine plays "));
+
+        // the engine takes its side's move straight away, and undo goes back to the player
+        let play = session("engine white 0.05\nundo");
+        assert_eq!(play.game.get_move_history().len(), 0);
+        let play = session("engine white 0.05");
+        assert_eq!(play.game.get_move_history().len(), 1);
+
+        let text = run("engine purple\nengine black soon\nengine black 1e30\n");
+        assert!(text.contains("the engine plays white, black or off"));
+        assert!(text.contains("'soon' is not a number of seconds"));
+        assert!(text.contains("'1e30' is not a number of seconds"));
+    }
+}
@@ -0,0 +1,275 @@
+//! Drawing the board as text, for terminals and logs.
+//!
+//! Every square is three characters wide. Without colours the squares of the last move are
+//! drawn in square brackets and the squares a piece can move to in round ones:
+//!
+//! ```text
+//! 8  r  n  b  q  k  b  n  r
+//! 7  p  p  p  p  p  p  p  p
+//! 6  .  .  .  .  .  .  .  .
+//! 5  .  .  .  .  .  .  .  .
+//! 4  .  .  .  . [P] .  .  .
+//! 3  .  .  .  .  .  .  .  .
+//! 2  P  P  P  P [.] P  P  P
+//! 1  R  N  B  Q  K  B  N  R
+//!    a  b  c  d  e  f  g  h
+//! ```
+
+use std::fmt;
+
+use crate::fen::piece_symbol;
+use crate::{Colour, Game, Piece, Square};
+
+const RESET: &str = "\x1b[0m";
+/// 256 colour backgrounds for the light and dark squares, in that order
+const SQUARE_COLOURS: [&str; 2] = ["\x1b[48;5;180m", "\x1b[48;5;137m"];
+const LAST_MOVE_COLOURS: [&str; 2] = ["\x1b[48;5;186m", "\x1b[48;5;143m"];
+const TARGET_COLOURS: [&str; 2] = ["\x1b[48;5;151m", "\x1b[48;5;108m"];
+const WHITE_PIECE: &str = "\x1b[97m";
+const BLACK_PIECE: &str = "\x1b[30m";
+
+/// How the pieces are drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Glyphs {
+    /// chess symbols, outlined for white (♔) and filled for black (♚)
+    Unicode,
+    /// FEN letters, uppercase for white and lowercase for black
+    Ascii,
+}
+
+/// What `Game::render` draws. The default is what `Display` shows: Unicode pieces with
+/// coordinates, from white's side and without colours or highlights.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub glyphs: Glyphs,
+    /// rank numbers on the left and file letters below
+    pub coordinates: bool,
+    /// the side at the bottom of the board
+    pub perspective: Colour,
+    /// light and dark squares in ANSI colours, for terminals that support 256 colours
+    pub colours: bool,
+    /// marks where the last move came from and went to
+    pub highlight_last_move: bool,
+    /// marks the squares the piece on this square can legally move to
+    pub highlight_moves_from: Option<Square>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            glyphs: Glyphs::Unicode,
+            coordinates: true,
+            perspective: Colour::White,
+            colours: false,
+            highlight_last_move: false,
+            highlight_moves_from: None,
+        }
+    }
+}
+
+/// How a square stands out.
+#[derive(Copy, Clone, PartialEq)]
+enum Highlight {
+    None,
+    LastMove,
+    Target,
+}
+
+impl Game {
+    /// Draws the board as text, one line per rank, as set out by the options.
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let mut highlights = [Highlight::None; 64];
+        if options.highlight_last_move {
+            if let Some(entry) = self.history.last() {
+                highlights[entry.mv.from.index()] = Highlight::LastMove;
+                highlights[entry.mv.to.index()] = Highlight::LastMove;
+            }
+        }
+        if let Some(from) = options.highlight_moves_from {
+            for mv in self.legal_moves_from(from) {
+                highlights[mv.to.index()] = Highlight::Target;
+            }
+        }
+
+        // the side at the bottom sees its first rank last and its left file first
+        let (ranks, files): (Vec<u8>, Vec<u8>) = match options.perspective {
+            Colour::White => ((0..8).rev().collect(), (0..8).collect()),
+            Colour::Black => ((0..8).collect(), (0..8).rev().collect()),
+        };
+
+        let mut text = String::new();
+        for &rank in &ranks {
+            if options.coordinates {
+                text += &format!("{} ", rank + 1);
+            }
+            for &file in &files {
+                let square = Square::new(file, rank).unwrap();
+                let piece = self.board[square.index()];
+                let highlight = highlights[square.index()];
+                text += &if options.colours {
+                    coloured_cell(piece, highlight, (file + rank) % 2 == 1, options.glyphs)
+                } else {
+                    plain_cell(piece, highlight, options.glyphs)
+                };
+            }
+            if options.colours {
+                text += RESET;
+            }
+            text = text.trim_end_matches(' ').to_string() + "\n";
+        }
+        if options.coordinates {
+            let letters: Vec<String> = files
+                .iter()
+                .map(|file| ((b'a' + file) as char).to_string())
+                .collect();
+            text += &format!("   {}\n", letters.join("  "));
+        }
+        text
+    }
+}
+
+/// a square without colours, with brackets around a highlighted one
+fn plain_cell(piece: Option<Piece>, highlight: Highlight, glyphs: Glyphs) -> String {
+    let symbol = match piece {
+        Some(piece) => glyph(piece, glyphs),
+        None if glyphs == Glyphs::Unicode => '·',
+        None => '.',
+    };
+    match highlight {
+        Highlight::None => format!(" {} ", symbol),
+        Highlight::LastMove => format!("[{}]", symbol),
+        Highlight::Target => format!("({})", symbol),
+    }
+}
+
+/// a square with its background and the piece in the colour of its side
+fn coloured_cell(
+    piece: Option<Piece>,
+    highlight: Highlight,
+    light: bool,
+    glyphs: Glyphs,
+) -> String {
+    let shade = if light { 0 } else { 1 };
+    let background = match highlight {
+        Highlight::None => SQUARE_COLOURS[shade],
+        Highlight::LastMove => LAST_MOVE_COLOURS[shade],
+        Highlight::Target => TARGET_COLOURS[shade],
+    };
+    match piece {
+        Some(piece) => {
+            let foreground = match Game::colour_of(Some(piece)) {
+                Some(Colour::White) => WHITE_PIECE,
+                _ => BLACK_PIECE,
+            };
+            format!("{}{} {} ", background, foreground, glyph(piece, glyphs))
+        }
+        None => format!("{}   ", background),
+    }
+}
+
+fn glyph(piece: Piece, glyphs: Glyphs) -> char {
+    match glyphs {
+        Glyphs::Ascii => piece_symbol(piece),
+        Glyphs::Unicode => match piece {
+            Piece::King(Colour::White) => '♔',
+            Piece::Queen(Colour::White) => '♕',
+            Piece::Rook(Colour::White) => '♖',
+            Piece::Bishop(Colour::White) => '♗',
+            Piece::Knight(Colour::White) => '♘',
+            Piece::Pawn(Colour::White) => '♙',
+            Piece::King(Colour::Black) => '♚',
+            Piece::Queen(Colour::Black) => '♛',
+            Piece::Rook(Colour::Black) => '♜',
+            Piece::Bishop(Colour::Black) => '♝',
+            Piece::Knight(Colour::Black) => '♞',
+            Piece::Pawn(Colour::Black) => '♟',
+        },
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(&RenderOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Glyphs, RenderOptions};
+    use crate::{Colour, Game, Square};
+
+    fn ascii() -> RenderOptions {
+        RenderOptions {
+            glyphs: Glyphs::Ascii,
+            ..RenderOptions::default()
+        }
+    }
+
+    #[test]
+    fn starting_position() {
+        let text = Game::new().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "8  ♜  ♞  ♝  ♛  ♚  ♝  ♞  ♜");
+        assert_eq!(lines[5], "3  ·  ·  ·  ·  ·  ·  ·  ·");
+        assert_eq!(lines[7], "1  ♖  ♘  ♗  ♕  ♔  ♗  ♘  ♖");
+        assert_eq!(lines[8], "   a  b  c  d  e  f  g  h");
+
+        let text = Game::new().render(&RenderOptions {
+            coordinates: false,
+            ..ascii()
+        });
+        assert_eq!(text.lines().next(), Some(" r  n  b  q  k  b  n  r"));
+        assert_eq!(text.lines().count(), 8);
+    }
+
+    #[test]
+    fn debug_shows_the_board() {
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        assert_eq!(format!("{:?}", game), game.to_string());
+    }
+
+    #[test]
+    fn flipped() {
+        let text = Game::new().render(&RenderOptions {
+            perspective: Colour::Black,
+            ..ascii()
+        });
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "1  R  N  B  K  Q  B  N  R");
+        assert_eq!(lines[7], "8  r  n  b  k  q  b  n  r");
+        assert_eq!(lines[8], "   h  g  f  e  d  c  b  a");
+    }
+
+    #[test]
+    fn highlights() {
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        let text = game.render(&RenderOptions {
+            highlight_last_move: true,
+            highlight_moves_from: Square::parse("g8"),
+            ..ascii()
+        });
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[2], "6  .  .  .  .  . (.) . (.)");
+        assert_eq!(lines[4], "4  .  .  .  . [P] .  .  .");
+        assert_eq!(lines[6], "2  P  P  P  P [.] P  P  P");
+
+        // no highlights unless asked for
+        assert!(!game.render(&ascii()).contains('['));
+    }
+
+    #[test]
+    fn colours() {
+        let text = Game::new().render(&RenderOptions {
+            colours: true,
+            ..ascii()
+        });
+        let first = text.lines().next().unwrap();
+        // a8 is a light square with a black rook on it
+        assert!(first.starts_with("8 \x1b[48;5;180m\x1b[30m r "));
+        assert!(first.ends_with("\x1b[0m"));
+        assert!(text.contains("\x1b[97m K "));
+    }
+}
@@ -0,0 +1,357 @@
+//! Standard Algebraic Notation (SAN), e.g. `Nf3`, `exd5`, `O-O` or `e8=Q+`.
+
+use crate::{ChessError, Colour, Game, GameState, Move, Piece, Square};
+
+impl Game {
+    /// Makes a move written in SAN, e.g. "Nf3", "exd5", "O-O" or "e8=Q+".
+    /// Check and checkmate suffixes are optional. Otherwise works like `make_move`.
+    pub fn make_san_move(&mut self, san: &str) -> Option<GameState> {
+        let result = self.try_make_san_move(san);
+        self.optional_state(result)
+    }
+
+    /// Same as `make_san_move`, but returns why a move was refused.
+    pub fn try_make_san_move(&mut self, san: &str) -> Result<GameState, ChessError> {
+        if self.is_game_over() {
+            return Err(ChessError::GameOver);
+        }
+
+        let mv = san_to_move(self, san).ok_or_else(|| ChessError::InvalidMove(san.to_string()))?;
+        self.play(mv)
+    }
+
+    /// Finds the legal move of the side to move that a SAN string describes.
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        if self.is_game_over() {
+            return None;
+        }
+        san_to_move(self, san)
+    }
+
+    /// Writes a legal move of the side to move in SAN, including the `+` or `#` suffix.
+    pub fn san(&self, mv: Move) -> Option<String> {
+        let mv = self
+            .legal_moves_from(mv.from)
+            .into_iter()
+            .find(|legal| legal.to == mv.to && legal.promotion == mv.promotion)?;
+        if self.is_game_over() {
+            return None;
+        }
+
+        let san = move_to_san(self, mv);
+
+        // make the move on a copy to see whether it gives check, leaving the clock alone
+        let mut game = self.clone();
+        game.apply_move(mv);
+        Some(with_suffix(san, &game))
+    }
+
+    /// Writes a legal move of the side to move in SAN, including the `+` or `#` suffix.
+    /// `_piece` is the promotion choice ("q", "r", "n" or "b") for a pawn reaching the last rank.
+    pub fn get_san(&self, _from: &str, _to: &str, _piece: Option<&str>) -> Option<String> {
+        let from = Square::parse(_from)?;
+        let to = Square::parse(_to)?;
+        let promotion = match _piece {
+            Some(piece) => Some(Game::promotion_piece(piece, self.active_colour)?),
+            None => None,
+        };
+
+        self.san(Move::new(from, to, promotion))
+    }
+
+    /// Returns every legal move of the side to move in SAN.
+    pub fn get_legal_moves_san(&self) -> Vec<String> {
+        self.legal_moves()
+            .into_iter()
+            .filter_map(|mv| self.san(mv))
+            .collect()
+    }
+}
+
+/// Writes a legal move of the side to move in SAN, without the check or checkmate suffix.
+pub(crate) fn move_to_san(game: &Game, mv: Move) -> String {
+    let (from, to) = (mv.from.index() as i32, mv.to.index() as i32);
+    let piece = match game.board[from as usize] {
+        Some(piece) => piece,
+        None => return String::new(),
+    };
+    let destination = mv.to.to_string();
+
+    match piece {
+        Piece::King(_) if (from - to).abs() == 2 => {
+            if to < from {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            }
+        }
+        Piece::Pawn(_) => {
+            let mut san = String::new();
+            // pawns only change file when capturing
+            if from % 8 != to % 8 {
+                san.push(file_letter(from));
+                san.push('x');
+            }
+            san += &destination;
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(piece_letter(promotion));
+            }
+            san
+        }
+        _ => {
+            let mut san = String::new();
+            san.push(piece_letter(piece));
+            san += &disambiguation(game, piece, from, to);
+            if game.board[to as usize].is_some() {
+                san.push('x');
+            }
+            san += &destination;
+            san
+        }
+    }
+}
+
+/// Adds the `+` or `#` suffix for the position a move led to.
+/// A check is still written when the move also ended the game in a draw.
+pub(crate) fn with_suffix(san: String, game: &Game) -> String {
+    if game.get_game_state() == GameState::CheckMate {
+        san + "#"
+    } else if game.check_checker(game.active_colour) {
+        san + "+"
+    } else {
+        san
+    }
+}
+
+/// Finds the legal move of the side to move that a SAN string describes.
+/// Check, checkmate and annotation suffixes (`+`, `#`, `!`, `?`) are ignored.
+pub(crate) fn san_to_move(game: &Game, san: &str) -> Option<Move> {
+    let colour = game.active_colour;
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+    // castling, allowing zeros as well since some programs write it that way
+    let home = match colour {
+        Colour::White => 3,
+        Colour::Black => 7 * 8 + 3,
+    };
+    let castling_target = match san {
+        "O-O" | "0-0" => Some(home - 2),
+        "O-O-O" | "0-0-0" => Some(home + 2),
+        _ => None,
+    };
+    if let Some(to) = castling_target {
+        if game.board[home as usize] == Some(Piece::King(colour))
+            && game.legal_destinations(home, colour).contains(&to)
+        {
+            return Some(game.describe_move(home, to, None));
+        }
+        return None;
+    }
+
+    // promotion is written as "e8=Q", but "e8Q" turns up too
+    let (body, promotion) = match san.split_once('=') {
+        Some((body, piece)) => (body, Some(piece)),
+        None => match san.char_indices().last() {
+            Some((index, 'Q' | 'R' | 'B' | 'N')) if index >= 2 => {
+                (&san[..index], Some(&san[index..]))
+            }
+            _ => (san, None),
+        },
+    };
+    let promotion = match promotion {
+        Some(letter) => Some(Game::promotion_piece(&letter.to_lowercase(), colour)?),
+        None => None,
+    };
+
+    if !body.is_ascii() || body.len() < 2 {
+        return None;
+    }
+    let (rest, destination) = body.split_at(body.len() - 2);
+    let to = Square::parse(destination)?.index() as i32;
+
+    let (piece, rest) = match rest.chars().next() {
+        Some('K') => (Piece::King(colour), &rest[1..]),
+        Some('Q') => (Piece::Queen(colour), &rest[1..]),
+        Some('R') => (Piece::Rook(colour), &rest[1..]),
+        Some('B') => (Piece::Bishop(colour), &rest[1..]),
+        Some('N') => (Piece::Knight(colour), &rest[1..]),
+        _ => (Piece::Pawn(colour), rest),
+    };
+
+    // whatever is left is the capture sign and the file and/or rank the piece comes from
+    let mut from_file = None;
+    let mut from_rank = None;
+    for symbol in rest.chars() {
+        match symbol {
+            'x' => (),
+            'a'..='h' if from_file.is_none() => from_file = Some(symbol),
+            '1'..='8' if from_rank.is_none() => from_rank = Some(symbol),
+            _ => return None,
+        }
+    }
+
+    let last_rank = to / 8 == 0 || to / 8 == 7;
+    if piece == Piece::Pawn(colour) && last_rank != promotion.is_some() {
+        return None;
+    }
+    if piece != Piece::Pawn(colour) && promotion.is_some() {
+        return None;
+    }
+
+    let mut candidates = (0..64).filter(|&from| {
+        let notation = Game::convert_to_notation(from);
+        game.board[from as usize] == Some(piece)
+            && from_file.is_none_or(|file| notation.starts_with(file))
+            && from_rank.is_none_or(|rank| notation.ends_with(rank))
+            && game.legal_destinations(from, colour).contains(&to)
+    });
+
+    // the move has to point out exactly one piece
+    match (candidates.next(), candidates.next()) {
+        (Some(from), None) => Some(game.describe_move(from, to, promotion)),
+        _ => None,
+    }
+}
+
+/// the file and/or rank needed to tell `from` apart from other pieces of the same kind that can reach `to`
+fn disambiguation(game: &Game, piece: Piece, from: i32, to: i32) -> String {
+    let others: Vec<i32> = (0..64)
+        .filter(|&other| {
+            other != from
+                && game.board[other as usize] == Some(piece)
+                && game
+                    .legal_destinations(other, game.active_colour)
+                    .contains(&to)
+        })
+        .collect();
+
+    if others.is_empty() {
+        return String::new();
+    }
+
+    let notation = Game::convert_to_notation(from);
+    if others.iter().all(|other| other % 8 != from % 8) {
+        notation[..1].to_string()
+    } else if others.iter().all(|other| other / 8 != from / 8) {
+        notation[1..].to_string()
+    } else {
+        notation
+    }
+}
+
+fn file_letter(position: i32) -> char {
+    Game::convert_to_notation(position).chars().next().unwrap()
+}
+
+fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::King(_) => 'K',
+        Piece::Queen(_) => 'Q',
+        Piece::Rook(_) => 'R',
+        Piece::Bishop(_) => 'B',
+        Piece::Knight(_) => 'N',
+        Piece::Pawn(_) => 'P',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Clock, Colour, Game, GameState, ManualTime, Piece, TimeControl};
+
+    #[test]
+    fn play_san_moves() {
+        let mut game = Game::new();
+        for san in ["e4", "d5", "exd5", "Nf6", "Nf3", "Nxd5", "Be2", "e6", "O-O"] {
+            game.make_san_move(san);
+        }
+
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkb1r/ppp2ppp/4p3/3n4/8/5N2/PPPPBPPP/RNBQ1RK1 b kq - 1 5"
+        );
+    }
+
+    #[test]
+    fn illegal_or_ambiguous_san() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+
+        // castling rights are gone
+        game.make_san_move("O-O");
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/R3K2R w - - 0 1");
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+
+        // both rooks can reach d1
+        game.make_san_move("Rd1");
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/4K3/R6R w - - 0 1");
+
+        game.make_san_move("Rhd1");
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/4K3/R2R4 b - - 1 1");
+    }
+
+    #[test]
+    fn disambiguation() {
+        // rooks on a1 and h1 share a rank, knights on b3 and b5 share a file
+        let game = Game::from_fen("4k3/8/8/1N6/8/1N6/4K3/R6R w - - 0 1").unwrap();
+        assert_eq!(game.get_san("a1", "d1", None), Some("Rad1".to_string()));
+        assert_eq!(game.get_san("b3", "d4", None), Some("N3d4".to_string()));
+        assert_eq!(game.get_san("b3", "d2", None), Some("Nd2".to_string()));
+
+        // three queens need both file and rank for one of them
+        let game = Game::from_fen("6k1/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1").unwrap();
+        assert_eq!(game.get_san("a4", "d4", None), Some("Qa4d4".to_string()));
+        assert_eq!(game.get_san("a1", "d4", None), Some("Q1d4".to_string()));
+        assert_eq!(game.get_san("h4", "d4", None), Some("Qhd4".to_string()));
+    }
+
+    #[test]
+    fn captures_checks_and_promotion() {
+        let game = Game::from_fen("3rk3/4P3/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            game.get_san("e7", "d8", Some("q")),
+            Some("exd8=Q+".to_string())
+        );
+        assert_eq!(
+            game.get_san("e7", "d8", Some("n")),
+            Some("exd8=N".to_string())
+        );
+        assert_eq!(game.get_san("a1", "a8", None), Some("Ra8".to_string()));
+        assert_eq!(game.get_san("e7", "d8", None), None);
+
+        let mut game = Game::from_fen("3rk3/4P3/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(game.make_san_move("exd8=Q+"), Some(GameState::Check));
+        assert_eq!(game.board[60], Some(Piece::Queen(Colour::White)));
+    }
+
+    #[test]
+    fn checkmate_suffix() {
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(game.get_san("d1", "d8", None), Some("Rd8#".to_string()));
+        assert_eq!(game.make_san_move("Rd8#"), Some(GameState::CheckMate));
+    }
+
+    #[test]
+    fn suffix_with_a_fallen_flag() {
+        let time = ManualTime::new();
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        game.set_clock(Clock::new(
+            TimeControl::sudden_death(Duration::from_secs(1)),
+            time.clone(),
+        ));
+        time.advance(Duration::from_secs(2));
+        assert_eq!(game.get_san("d1", "d8", None), Some("Qd8+".to_string()));
+    }
+
+    #[test]
+    fn legal_moves_in_san() {
+        let game = Game::new();
+        let moves = game.get_legal_moves_san();
+
+        assert_eq!(moves.len(), 20);
+        assert!(moves.contains(&"Nf3".to_string()));
+        assert!(moves.contains(&"e4".to_string()));
+    }
+}
@@ -0,0 +1,283 @@
+//! Saving and restoring games with serde, behind the `serde` feature.
+//!
+//! A game is stored as the position it started from plus the moves played since, so it comes
+//! back with its full history: undo, repetitions and PGN export work as before. In JSON:
+//!
+//! ```text
+//! {"moves":["e2e4","e7e5"],"state":"InProgress",
+//!  "fen":"rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"}
+//! ```
+//!
+//! The current position is saved as FEN too, for readers that don't replay the moves. A clock
+//! isn't saved, as it reads the time from a `TimeSource`.
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::{Colour, Game, GameState, Outcome, Square, Termination};
+
+/// What is stored for a game.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    /// the FEN the game was set up from, left out for the normal starting position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    /// the moves played so far in coordinate notation, oldest first
+    moves: Vec<String>,
+    /// moves taken back that can be redone, the next one is last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redo: Vec<String>,
+    state: GameState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    outcome: Option<Outcome>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    draw_offer: Option<Colour>,
+    /// the position after the moves, checked when the game is restored
+    fen: String,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SavedGame {
+            start: self.start_fen.clone(),
+            moves: self
+                .history
+                .iter()
+                .map(|entry| entry.mv.to_string())
+                .collect(),
+            redo: self.redo_stack.iter().map(|mv| mv.to_string()).collect(),
+            state: self.state,
+            outcome: self.get_outcome(),
+            draw_offer: self.draw_offer,
+            fen: self.to_fen(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        let saved = SavedGame::deserialize(deserializer)?;
+        let mut game = match &saved.start {
+            Some(fen) => Game::from_fen(fen).map_err(de::Error::custom)?,
+            None => Game::new(),
+        };
+
+        for notation in &saved.moves {
+            let mv = game
+                .parse_move(notation)
+                .ok_or_else(|| de::Error::custom(format!("illegal move {}", notation)))?;
+            game.apply_move(mv);
+        }
+        if game.to_fen() != saved.fen {
+            return Err(de::Error::custom(
+                "the moves don't lead to the saved position",
+            ));
+        }
+
+        // redone moves have to be legal once the moves after them are taken back
+        let mut replay = game.clone();
+        for notation in saved.redo.iter().rev() {
+            let mv = replay
+                .parse_move(notation)
+                .ok_or_else(|| de::Error::custom(format!("illegal move {}", notation)))?;
+            replay.apply_move(mv);
+            game.redo_stack.insert(0, mv);
+        }
+
+        // how the game ended isn't always on the board, e.g. after resigning or claiming a draw,
+        // so a finished state is only taken from the save when the outcome backs it up
+        if saved.state != game.state {
+            let possible = !game.is_game_over()
+                && match (saved.state, saved.outcome) {
+                    (GameState::GameOver, Some(outcome)) => {
+                        outcome.winner.is_some()
+                            && matches!(
+                                outcome.termination,
+                                Termination::Resignation | Termination::Timeout
+                            )
+                    }
+                    (GameState::Draw, Some(outcome)) => {
+                        outcome.winner.is_none()
+                            && matches!(
+                                outcome.termination,
+                                Termination::Agreement | Termination::Timeout
+                            )
+                    }
+                    (GameState::Repetition, Some(_)) => game.get_repetition_count() >= 3,
+                    (GameState::FiftyMoveRule, Some(_)) => game.get_halfmove_clock() >= 100,
+                    _ => false,
+                };
+            if !possible {
+                return Err(de::Error::custom(format!(
+                    "the position isn't {:?}",
+                    saved.state
+                )));
+            }
+            game.state = saved.state;
+            if matches!(saved.state, GameState::GameOver | GameState::Draw) {
+                game.outcome = saved.outcome;
+            }
+        }
+        if game.get_outcome() != saved.outcome {
+            return Err(de::Error::custom("the outcome doesn't match the game"));
+        }
+        game.draw_offer = saved.draw_offer;
+        Ok(game)
+    }
+}
+
+/// Squares are written in notation, like "e4".
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Square, D::Error> {
+        let notation = String::deserialize(deserializer)?;
+        Square::parse(&notation)
+            .ok_or_else(|| de::Error::custom(format!("'{}' is not a square", notation)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Colour, Game, GameState, Move, Piece, Square};
+
+    fn round_trip(game: &Game) -> Game {
+        let json = serde_json::to_string(game).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn compact_json() {
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        game.make_move("e7", "e5");
+        assert_eq!(
+            serde_json::to_string(&game).unwrap(),
+            "{\"moves\":[\"e2e4\",\"e7e5\"],\"state\":\"InProgress\",\
+             \"fen\":\"rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2\"}"
+        );
+    }
+
+    #[test]
+    fn restores_the_history() {
+        let mut game = Game::new();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Be7"] {
+            game.make_san_move(san);
+        }
+        game.undo_move();
+        game.undo_move();
+        game.offer_draw(Colour::White).unwrap();
+
+        let mut restored = round_trip(&game);
+        assert_eq!(restored.to_fen(), game.to_fen());
+        assert_eq!(restored.hash(), game.hash());
+        assert_eq!(restored.get_move_history(), game.get_move_history());
+        assert_eq!(restored.get_draw_offer(), Some(Colour::White));
+        assert_eq!(restored.to_pgn(&[]), game.to_pgn(&[]));
+
+        restored.redo_move();
+        restored.redo_move();
+        game.redo_move();
+        game.redo_move();
+        assert_eq!(restored.to_fen(), game.to_fen());
+        assert!(restored.redo_move().is_none());
+    }
+
+    #[test]
+    fn restores_finished_games() {
+        let mut game = Game::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        game.make_move_with_promotion("a7", "a8", "q");
+        game.resign(Colour::Black).unwrap();
+
+        let restored = round_trip(&game);
+        assert_eq!(restored.get_game_state(), GameState::GameOver);
+        assert_eq!(restored.get_outcome(), game.get_outcome());
+        assert_eq!(
+            restored.get_piece(Square::parse("a8").unwrap()),
+            Some(Piece::Queen(Colour::White))
+        );
+        assert!(restored.to_fen().ends_with("b - - 0 1"));
+    }
+
+    #[test]
+    fn restores_claimed_draws() {
+        let mut game = Game::new();
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"] {
+            game.make_san_move(san);
+        }
+        game.claim_draw().unwrap();
+
+        let restored = round_trip(&game);
+        assert_eq!(restored.get_game_state(), GameState::Repetition);
+        assert_eq!(restored.get_outcome(), game.get_outcome());
+    }
+
+    #[test]
+    fn rejects_bad_games() {
+        let illegal = "{\"moves\":[\"e2e5\"],\"state\":\"InProgress\",\"fen\":\"\"}";
+        let error = serde_json::from_str::<Game>(illegal).unwrap_err();
+        assert!(error.to_string().contains("illegal move e2e5"));
+
+        let wrong_fen = "{\"moves\":[\"e2e4\"],\"state\":\"InProgress\",\
+                         \"fen\":\"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"}";
+        assert!(serde_json::from_str::<Game>(wrong_fen).is_err());
+
+        let bad_start = "{\"start\":\"8/8\",\"moves\":[],\"state\":\"InProgress\",\"fen\":\"\"}";
+        assert!(serde_json::from_str::<Game>(bad_start).is_err());
+
+        // the state has to fit the position
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mated = format!(
+            "{{\"moves\":[\"f2f3\",\"e7e5\",\"g2g4\",\"d8h4\"],\"state\":\"InProgress\",\
+             \"fen\":\"{}\"}}",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"
+        );
+        for json in [
+            format!(
+                "{{\"moves\":[],\"state\":\"CheckMate\",\"fen\":\"{}\"}}",
+                start
+            ),
+            format!(
+                "{{\"moves\":[],\"state\":\"GameOver\",\"fen\":\"{}\"}}",
+                start
+            ),
+            format!(
+                "{{\"moves\":[],\"state\":\"Repetition\",\"outcome\":\
+                 {{\"winner\":null,\"termination\":\"Repetition\"}},\"fen\":\"{}\"}}",
+                start
+            ),
+            format!(
+                "{{\"moves\":[],\"state\":\"InProgress\",\"outcome\":\
+                 {{\"winner\":\"White\",\"termination\":\"Resignation\"}},\"fen\":\"{}\"}}",
+                start
+            ),
+            mated,
+        ] {
+            assert!(serde_json::from_str::<Game>(&json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn pieces_and_moves() {
+        let mv = Game::new().parse_move("g1f3").unwrap();
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(
+            json,
+            "{\"from\":\"g1\",\"to\":\"f3\",\"promotion\":null,\"capture\":false,\
+             \"castle\":false,\"en_passant\":false}"
+        );
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+
+        assert_eq!(
+            serde_json::to_string(&Piece::Knight(Colour::Black)).unwrap(),
+            "{\"Knight\":\"Black\"}"
+        );
+        assert!(serde_json::from_str::<Square>("\"i9\"").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! A typed square on the board, used instead of notation strings like "e4".
+
+use std::fmt;
+
+/// A square on the board.
+///
+/// Files and ranks are counted from zero the way they're read by a player,
+/// so a1 is file 0, rank 0 and h8 is file 7, rank 7.
+/// The index matches `Game`'s board, `rank * 8 + file` with the files backwards (h is 0).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// Returns the square on the given file (0 = a) and rank (0 = first rank).
+    pub fn new(file: u8, rank: u8) -> Option<Square> {
+        if file < 8 && rank < 8 {
+            Some(Square(rank * 8 + 7 - file))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the square at a position on the board, from 0 to 63.
+    pub fn from_index(index: usize) -> Option<Square> {
+        if index < 64 {
+            Some(Square(index as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Reads standard chess notation for a single square, e.g. "e4".
+    pub fn parse(notation: &str) -> Option<Square> {
+        let mut chars = notation.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None;
+        }
+
+        Square::new(file as u8 - b'a', rank as u8 - b'1')
+    }
+
+    /// The position on the board, `rank * 8 + file` with h as file 0.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The file counted from the a-file, which is 0.
+    pub fn file(self) -> u8 {
+        7 - self.0 % 8
+    }
+
+    /// The rank counted from the first rank, which is 0.
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Square;
+    use crate::Game;
+
+    #[test]
+    fn matches_board_positions() {
+        for index in 0..64 {
+            let square = Square::from_index(index).unwrap();
+            assert_eq!(square.to_string(), Game::convert_to_notation(index as i32));
+            assert_eq!(Square::parse(&square.to_string()), Some(square));
+        }
+    }
+
+    #[test]
+    fn files_and_ranks() {
+        let square = Square::parse("e4").unwrap();
+        assert_eq!(square.file(), 4);
+        assert_eq!(square.rank(), 3);
+        assert_eq!(Square::new(4, 3), Some(square));
+        assert_eq!(square.index(), 27);
+
+        assert_eq!(Square::parse("i1"), None);
+        assert_eq!(Square::parse("a9"), None);
+        assert_eq!(Square::parse("a10"), None);
+        assert_eq!(Square::new(8, 0), None);
+        assert_eq!(Square::from_index(64), None);
+    }
+}
@@ -0,0 +1,399 @@
+//! The Universal Chess Interface (UCI), which chess GUIs and tournament runners use to talk to
+//! engines: commands come in on one line each, and the engine answers with lines of its own.
+//!
+//! ```text
+//! > uci
+//! < id name wingmyr-chess 0.1.0
+//! < uciok
+//! > position startpos moves e2e4
+//! > go movetime 1000
+//! < info depth 1 score cp 30 nodes 21 nps 21000 time 1 pv d7d5
+//! < bestmove e7e5
+//! ```
+//!
+//! Searches run on their own thread, so `stop` and `isready` are answered while the engine thinks.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::engine::time_for_move;
+use crate::{Colour, Engine, Game, Score, SearchLimits, SearchResult, Weights};
+
+/// Runs a UCI engine that reads commands from `input` until `quit` or the end of the input,
+/// and writes its replies to `output`. A search that is still running when the input ends is
+/// finished first, unless it's an infinite one.
+pub fn run_uci(input: impl BufRead, output: impl Write + Send + 'static) -> io::Result<()> {
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        if !uci.handle(&line?)? {
+            break;
+        }
+    }
+    uci.finish_search(false);
+    Ok(())
+}
+
+struct Uci<W> {
+    output: Arc<Mutex<W>>,
+    game: Game,
+    /// `None` while it's searching on its own thread
+    engine: Option<Engine>,
+    search: Option<Search>,
+    stop: Arc<AtomicBool>,
+}
+
+/// A search running on its own thread, which hands the engine back when it's done.
+struct Search {
+    thread: JoinHandle<Engine>,
+    /// an infinite search only ends with `stop`
+    infinite: bool,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    fn new(output: W) -> Uci<W> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut engine = Engine::new();
+        engine.set_stop_signal(stop.clone());
+        Uci {
+            output: Arc::new(Mutex::new(output)),
+            game: Game::new(),
+            engine: Some(engine),
+            search: None,
+            stop,
+        }
+    }
+
+    /// handles a line of input, and returns false once the engine should quit
+    fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                send(
+                    &self.output,
+                    &format!(
+                        "id name {} {}",
+                        env!("CARGO_PKG_NAME"),
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                )?;
+                send(&self.output, "id author the wingmyr-chess developers")?;
+                send(
+                    &self.output,
+                    "option name Hash type spin default 16 min 1 max 4096",
+                )?;
+                send(&self.output, "option name Clear Hash type button")?;
+                send(
+                    &self.output,
+                    "option name Weights type string default <empty>",
+                )?;
+                send(&self.output, "uciok")?;
+            }
+            Some("isready") => send(&self.output, "readyok")?,
+            Some("ucinewgame") => {
+                self.engine().clear();
+                self.game = Game::new();
+            }
+            Some("setoption") => self.set_option(&tokens[1..])?,
+            Some("position") => self.position(&tokens[1..])?,
+            Some("go") => self.go(&tokens[1..]),
+            Some("stop") => self.finish_search(true),
+            Some("quit") => {
+                self.finish_search(true);
+                return Ok(false);
+            }
+            // anything else is ignored, as the protocol asks
+            _ => (),
+        }
+        Ok(true)
+    }
+
+    /// the engine, once any search that doesn't have to be stopped has finished
+    fn engine(&mut self) -> &mut Engine {
+        self.finish_search(false);
+        self.engine.as_mut().unwrap()
+    }
+
+    /// waits for the running search to end, stopping it if asked to or if it would never end
+    fn finish_search(&mut self, stop: bool) {
+        if let Some(search) = self.search.take() {
+            if stop || search.infinite {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+            self.engine = Some(search.thread.join().expect("the search thread panicked"));
+        }
+    }
+
+    /// `setoption name <name> [value <value>]`
+    fn set_option(&mut self, tokens: &[&str]) -> io::Result<()> {
+        let value_at = tokens.iter().position(|&token| token == "value");
+        let name = tokens[..value_at.unwrap_or(tokens.len())]
+            .iter()
+            .skip_while(|&&token| token == "name")
+            .copied()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let value = value_at.map_or(String::new(), |at| tokens[at + 1..].join(" "));
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(megabytes) if megabytes > 0 => self.engine().set_hash_size(megabytes),
+                _ => send(
+                    &self.output,
+                    &format!("info string invalid hash size '{}'", value),
+                )?,
+            },
+            "clear hash" => self.engine().clear(),
+            "weights" => match Weights::load(&value) {
+                Ok(weights) => self.engine().set_weights(weights),
+                Err(error) => send(
+                    &self.output,
+                    &format!("info string can't load weights from '{}': {}", value, error),
+                )?,
+            },
+            _ => send(
+                &self.output,
+                &format!("info string unknown option '{}'", name),
+            )?,
+        }
+        Ok(())
+    }
+
+    /// `position startpos|fen <fen> [moves <move>...]`
+    fn position(&mut self, tokens: &[&str]) -> io::Result<()> {
+        self.finish_search(false);
+        let moves_at = tokens
+            .iter()
+            .position(|&token| token == "moves")
+            .unwrap_or(tokens.len());
+
+        let mut game = match tokens.first().copied() {
+            Some("startpos") => Game::new(),
+            Some("fen") => match Game::from_fen(&tokens[1..moves_at].join(" ")) {
+                Ok(game) => game,
+                Err(error) => {
+                    return send(&self.output, &format!("info string invalid fen: {}", error))
+                }
+            },
+            _ => return send(&self.output, "info string expected startpos or fen"),
+        };
+
+        for notation in tokens.iter().skip(moves_at + 1) {
+            match game.parse_move(notation) {
+                Some(mv) if game.play(mv).is_ok() => (),
+                _ => {
+                    send(
+                        &self.output,
+                        &format!("info string illegal move {}", notation),
+                    )?;
+                    break;
+                }
+            }
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    /// `go [depth <plies>] [nodes <n>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>]
+    /// [binc <ms>] [movestogo <n>] [infinite]`, a plain `go` searches until `stop`
+    fn go(&mut self, tokens: &[&str]) {
+        let mut engine = match self.engine.take() {
+            Some(engine) => engine,
+            None => {
+                self.finish_search(false);
+                self.engine.take().unwrap()
+            }
+        };
+
+        let number = |name: &str| -> Option<u64> {
+            let at = tokens.iter().position(|&token| token == name)?;
+            tokens.get(at + 1)?.parse().ok()
+        };
+        let millis = |name: &str| number(name).map(Duration::from_millis);
+
+        let mut limits = SearchLimits {
+            depth: number("depth").map(|depth| depth as u32),
+            nodes: number("nodes"),
+            time: millis("movetime"),
+        };
+        let (remaining, increment) = match self.game.active_colour {
+            Colour::White => (millis("wtime"), millis("winc")),
+            Colour::Black => (millis("btime"), millis("binc")),
+        };
+        if let Some(remaining) = remaining {
+            let moves_to_go = number("movestogo").map(|moves| moves as u32);
+            let time = time_for_move(remaining, increment.unwrap_or_default(), moves_to_go);
+            limits.time = Some(limits.time.map_or(time, |movetime| movetime.min(time)));
+        }
+        let infinite = tokens.contains(&"infinite") || limits == SearchLimits::default();
+
+        self.stop.store(false, Ordering::Relaxed);
+        let output = self.output.clone();
+        let stop = self.stop.clone();
+        let game = self.game.clone();
+        let thread = thread::spawn(move || {
+            let started = Instant::now();
+            let result = engine.search_with_progress(&game, limits, |result| {
+                let _ = send(&output, &info(result, started.elapsed()));
+            });
+
+            // the best move of an infinite search is only given once the GUI asks for it
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let best_move = result
+                .best_move
+                .map_or("0000".to_string(), |mv| mv.to_string());
+            // a broken output shows up again on the main thread
+            let _ = send(&output, &format!("bestmove {}", best_move));
+            engine
+        });
+        self.search = Some(Search { thread, infinite });
+    }
+}
+
+/// the `info` line for a finished depth
+fn info(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match result.score {
+        Score::Centipawns(score) => format!("cp {}", score),
+        Score::Mate(moves) => format!("mate {}", moves),
+    };
+    let millis = elapsed.as_millis() as u64;
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        result.nodes * 1000 / millis.max(1),
+        millis,
+        pv.join(" ")
+    )
+}
+
+fn send<W: Write>(output: &Mutex<W>, line: &str) -> io::Result<()> {
+    let mut output = output.lock().unwrap();
+    writeln!(output, "{}", line)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use super::run_uci;
+    use crate::Game;
+
+    /// collects what the engine writes, so a test can read it afterwards
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// runs a script of commands and returns the lines the engine wrote
+    fn run(script: &str) -> Vec<String> {
+        let output = Output::default();
+        run_uci(script.as_bytes(), output.clone()).unwrap();
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn best_move(lines: &[String]) -> &str {
+        lines
+            .iter()
+            .find_map(|line| line.strip_prefix("bestmove "))
+            .unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let lines = run("uci\nisready\nquit\n");
+        assert!(lines[0].starts_with("id name wingmyr-chess"));
+        assert!(lines.contains(&"option name Hash type spin default 16 min 1 max 4096".to_string()));
+        assert_eq!(lines[lines.len() - 2], "uciok");
+        assert_eq!(lines[lines.len() - 1], "readyok");
+    }
+
+    #[test]
+    fn search_a_position() {
+        let lines = run("ucinewgame\nposition startpos moves e2e4 e7e5\ngo depth 3\n");
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        game.make_move("e7", "e5");
+        assert!(game.parse_move(best_move(&lines)).is_some());
+
+        // one info line per depth
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("info depth"))
+                .count(),
+            3
+        );
+        assert!(lines[0].starts_with("info depth 1 score cp "));
+    }
+
+    #[test]
+    fn mate_from_fen() {
+        let lines = run("position fen 6k1/5ppp/8/8/8/8/8/3RK3 w - - 0 1\ngo depth 2\n");
+        assert_eq!(best_move(&lines), "d1d8");
+        assert!(lines.iter().any(|line| line.contains("score mate 1")));
+
+        // mated already, there is no move to give
+        let lines = run("position fen 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1\ngo depth 2\n");
+        assert_eq!(best_move(&lines), "0000");
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let lines = run("position startpos\ngo infinite\nisready\nstop\nquit\n");
+        assert!(lines.contains(&"readyok".to_string()));
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("bestmove"))
+                .count(),
+            1
+        );
+        assert!(Game::new().parse_move(best_move(&lines)).is_some());
+    }
+
+    #[test]
+    fn clock_and_options() {
+        let lines = run(
+            "setoption name Hash value 1\nsetoption name Clear Hash\nsetoption name Nonsense value 3\n\
+             position startpos moves g1f3\ngo wtime 200 btime 200 winc 0 binc 0\n",
+        );
+        assert!(lines.contains(&"info string unknown option 'Nonsense'".to_string()));
+        let mut game = Game::new();
+        game.make_move("g1", "f3");
+        assert!(game.parse_move(best_move(&lines)).is_some());
+
+        let lines = run("setoption name Weights value /nonexistent/weights.txt\n");
+        assert!(lines[0].starts_with("info string can't load weights"));
+    }
+
+    #[test]
+    fn bad_positions() {
+        let lines = run("position startpos moves e2e4 e2e4\ngo depth 1\n");
+        assert_eq!(lines[0], "info string illegal move e2e4");
+        // the moves before the illegal one are kept, so black is to move
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        assert!(game.parse_move(best_move(&lines)).is_some());
+
+        let lines = run("position fen not a fen\n");
+        assert!(lines[0].starts_with("info string invalid fen"));
+    }
+}
@@ -0,0 +1,443 @@
+//! The Chess Engine Communication Protocol (CECP), also called the XBoard protocol, for tools
+//! that don't speak UCI. Unlike UCI the engine keeps the game itself: the GUI sends the
+//! opponent's moves and the engine answers with its own once it's its turn.
+//!
+//! ```text
+//! > xboard
+//! > protover 2
+//! < feature myname="wingmyr-chess 0.1.0" setboard=1 usermove=1 ping=1 ... done=1
+//! > new
+//! > usermove e2e4
+//! < move e7e5
+//! ```
+//!
+//! The engine thinks on the thread reading the commands, so the ones sent while it thinks are
+//! answered once it has moved.
+
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use crate::engine::time_for_move;
+use crate::{ChessError, Colour, Engine, Game, GameState, Score, SearchLimits, SearchResult};
+
+/// Mate scores are sent as this plus the number of moves to the mate.
+const MATE_SCORE: i32 = 100_000;
+
+/// Runs a CECP engine that reads commands from `input` until `quit` or the end of the input,
+/// and writes its replies to `output`.
+pub fn run_xboard(input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut xboard = XBoard::new(output);
+    for line in input.lines() {
+        if !xboard.handle(&line?)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct XBoard<W> {
+    output: W,
+    game: Game,
+    engine: Engine,
+    /// the side the engine plays, `None` in force mode where it only keeps track of the moves
+    engine_colour: Option<Colour>,
+    /// from `level`, the moves in each time control, 0 for the whole game
+    moves_per_control: u32,
+    increment: Duration,
+    /// the time left on the engine's clock, set by `level` and `time`
+    remaining: Duration,
+    /// from `st`, a fixed time for every move
+    move_time: Option<Duration>,
+    /// from `sd`
+    depth: Option<u32>,
+    /// whether to send thinking output, switched by `post` and `nopost`
+    post: bool,
+}
+
+impl<W: Write> XBoard<W> {
+    fn new(output: W) -> XBoard<W> {
+        XBoard {
+            output,
+            game: Game::new(),
+            engine: Engine::new(),
+            engine_colour: Some(Colour::Black),
+            // the time control xboard starts with, 40 moves in 5 minutes
+            moves_per_control: 40,
+            increment: Duration::ZERO,
+            remaining: Duration::from_secs(5 * 60),
+            move_time: None,
+            depth: None,
+            post: false,
+        }
+    }
+
+    /// handles a line of input, and returns false once the engine should quit
+    fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let (command, arguments) = line
+            .trim()
+            .split_once(' ')
+            .map_or((line.trim(), ""), |(command, arguments)| {
+                (command, arguments.trim())
+            });
+
+        match command {
+            "protover" => writeln!(
+                self.output,
+                "feature myname=\"{} {}\" setboard=1 usermove=1 ping=1 playother=1 colors=0 \
+                 analyze=0 sigint=0 sigterm=0 done=1",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )?,
+            "new" => {
+                self.game = Game::new();
+                self.engine.clear();
+                self.engine_colour = Some(Colour::Black);
+                self.depth = None;
+            }
+            "force" => self.engine_colour = None,
+            "go" => {
+                self.engine_colour = Some(self.game.active_colour);
+                self.think()?;
+            }
+            "playother" => self.engine_colour = Some(other_colour(self.game.active_colour)),
+            "usermove" => self.user_move(arguments)?,
+            "setboard" => match Game::from_fen(arguments) {
+                Ok(game) => self.game = game,
+                Err(_) => writeln!(self.output, "tellusererror Illegal position")?,
+            },
+            "undo" => {
+                self.game.undo_move();
+            }
+            "remove" => {
+                self.game.undo_move();
+                self.game.undo_move();
+            }
+            "level" => {
+                if let Err(reason) = self.level(arguments) {
+                    self.error(reason, line)?
+                }
+            }
+            "st" => match parse_seconds(arguments) {
+                Some(time) => self.move_time = Some(time),
+                None => self.error("bad time", line)?,
+            },
+            "sd" => match arguments.parse() {
+                Ok(depth) => self.depth = Some(depth),
+                Err(_) => self.error("bad depth", line)?,
+            },
+            // in centiseconds
+            "time" => match arguments
+                .parse::<u64>()
+                .ok()
+                .and_then(|time| time.checked_mul(10))
+            {
+                Some(time) => self.remaining = Duration::from_millis(time),
+                None => self.error("bad time", line)?,
+            },
+            "ping" => writeln!(self.output, "pong {}", arguments)?,
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            // the game is over, and the engine shouldn't move anymore
+            "result" => self.engine_colour = None,
+            "quit" => return Ok(false),
+            // commands that don't change anything for this engine
+            "" | "xboard" | "accepted" | "rejected" | "otim" | "hard" | "easy" | "random"
+            | "computer" | "name" | "rating" | "ics" => (),
+            _ => self.error("unknown command", line)?,
+        }
+        Ok(true)
+    }
+
+    fn error(&mut self, reason: &str, command: &str) -> io::Result<()> {
+        writeln!(self.output, "Error ({}): {}", reason, command)
+    }
+
+    /// the opponent's move, which the engine answers if it's playing
+    fn user_move(&mut self, notation: &str) -> io::Result<()> {
+        if let Err(error) = play(&mut self.game, notation) {
+            return writeln!(self.output, "Illegal move ({}): {}", error, notation);
+        }
+        self.report_result()?;
+        if self.engine_colour == Some(self.game.active_colour) {
+            self.think()?;
+        }
+        Ok(())
+    }
+
+    /// `level <moves per control> <minutes[:seconds]> <increment in seconds>`, or why it
+    /// can't be read
+    fn level(&mut self, arguments: &str) -> Result<(), &'static str> {
+        let fields: Vec<&str> = arguments.split_whitespace().collect();
+        let (moves, base, increment) = match fields[..] {
+            [moves, base, increment] => (moves, base, increment),
+            _ => return Err("bad level"),
+        };
+        let moves = moves.parse().map_err(|_| "bad level")?;
+        let base = parse_minutes(base).ok_or("bad level")?;
+        let increment = match increment.parse::<f64>() {
+            Ok(_) => parse_seconds(increment).ok_or("bad time")?,
+            Err(_) => return Err("bad level"),
+        };
+
+        self.moves_per_control = moves;
+        self.remaining = base;
+        self.increment = increment;
+        self.move_time = None;
+        Ok(())
+    }
+
+    /// the limits for the engine's next search
+    fn limits(&self) -> SearchLimits {
+        let time = self.move_time.unwrap_or_else(|| {
+            let moves_to_go = match self.moves_per_control {
+                0 => None,
+                moves => Some(moves - (self.game.get_fullmove_number() - 1) % moves),
+            };
+            time_for_move(self.remaining, self.increment, moves_to_go)
+        });
+        SearchLimits {
+            depth: self.depth,
+            nodes: None,
+            time: Some(time),
+        }
+    }
+
+    /// searches for the engine's move and plays it
+    fn think(&mut self) -> io::Result<()> {
+        if self.game.is_game_over() {
+            return Ok(());
+        }
+
+        let limits = self.limits();
+        let (output, post) = (&mut self.output, self.post);
+        let mut written = Ok(());
+        let started = Instant::now();
+        let result = self
+            .engine
+            .search_with_progress(&self.game, limits, |result| {
+                if post && written.is_ok() {
+                    written = writeln!(output, "{}", thinking(result, started.elapsed()));
+                }
+            });
+        written?;
+
+        if let Some(mv) = result.best_move {
+            let notation = mv.to_string();
+            play(&mut self.game, &notation).expect("the engine found an illegal move");
+            writeln!(self.output, "move {}", notation)?;
+            self.report_result()?;
+        }
+        Ok(())
+    }
+
+    /// tells the GUI once the game is over
+    fn report_result(&mut self) -> io::Result<()> {
+        if let Some(outcome) = self.game.get_outcome() {
+            let result = match outcome.winner {
+                Some(Colour::White) => "1-0",
+                Some(Colour::Black) => "0-1",
+                None => "1/2-1/2",
+            };
+            writeln!(self.output, "{} {{{}}}", result, outcome)?;
+        }
+        Ok(())
+    }
+}
+
+/// plays a move in coordinate notation, like "e2e4" or "e7e8q"
+fn play(game: &mut Game, notation: &str) -> Result<GameState, ChessError> {
+    let invalid = || ChessError::InvalidMove(notation.to_string());
+    let from = notation.get(0..2).ok_or_else(invalid)?;
+    let to = notation.get(2..4).ok_or_else(invalid)?;
+    match notation.get(4..) {
+        Some("") => game.try_make_move(from, to),
+        Some(piece) => game.try_make_move_with_promotion(from, to, piece),
+        None => Err(invalid()),
+    }
+}
+
+/// reads the base time of `level`, in minutes with optional seconds like "5" or "0:30"
+fn parse_minutes(text: &str) -> Option<Duration> {
+    let (minutes, seconds) = text.split_once(':').unwrap_or((text, "0"));
+    let seconds = minutes
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(60)?
+        .checked_add(seconds.parse::<u64>().ok()?)?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// a number of seconds that may have a fraction, `None` if it's negative or too large
+fn parse_seconds(text: &str) -> Option<Duration> {
+    let seconds = text.parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// a line of thinking output: depth, score in centipawns, time in centiseconds, nodes and the
+/// principal variation
+fn thinking(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match result.score {
+        Score::Centipawns(score) => score,
+        Score::Mate(moves) if moves > 0 => MATE_SCORE + moves,
+        Score::Mate(moves) => -MATE_SCORE + moves,
+    };
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    format!(
+        "{} {} {} {} {}",
+        result.depth,
+        score,
+        elapsed.as_millis() / 10,
+        result.nodes,
+        pv.join(" ")
+    )
+}
+
+fn other_colour(colour: Colour) -> Colour {
+    match colour {
+        Colour::White => Colour::Black,
+        Colour::Black => Colour::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_minutes, run_xboard, XBoard};
+    use crate::{Colour, Game, SearchLimits};
+
+    /// runs a script of commands and returns the lines the engine wrote
+    fn run(script: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run_xboard(script.as_bytes(), &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+
+    /// feeds commands to an engine and returns it, to look at its game
+    fn session(script: &str) -> XBoard<Vec<u8>> {
+        let mut xboard = XBoard::new(Vec::new());
+        for line in script.lines() {
+            assert!(xboard.handle(line).unwrap());
+        }
+        xboard
+    }
+
+    fn engine_move(lines: &[String]) -> &str {
+        lines
+            .iter()
+            .find_map(|line| line.strip_prefix("move "))
+            .unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let lines = run("xboard\nprotover 2\naccepted setboard\nping 7\nnonsense\nquit\n");
+        assert!(lines[0].starts_with("feature myname=\"wingmyr-chess"));
+        assert!(lines[0].ends_with("done=1"));
+        assert_eq!(lines[1], "pong 7");
+        assert_eq!(lines[2], "Error (unknown command): nonsense");
+    }
+
+    #[test]
+    fn answers_user_moves() {
+        let lines = run("new\nsd 2\nusermove e2e4\n");
+        let mut game = Game::new();
+        game.make_move("e2", "e4");
+        assert!(game.parse_move(engine_move(&lines)).is_some());
+
+        // the engine's move is part of the game
+        let xboard = session("new\nsd 2\nusermove e2e4");
+        assert_eq!(xboard.game.get_move_history().len(), 2);
+        assert_eq!(xboard.game.active_colour, Colour::White);
+    }
+
+    #[test]
+    fn force_mode_and_go() {
+        let xboard = session("new\nforce\nusermove e2e4\nusermove e7e5");
+        assert_eq!(xboard.game.get_move_history().len(), 2);
+        assert!(xboard.output.is_empty());
+
+        let xboard = session("new\nforce\nusermove e2e4\nsd 2\ngo\nusermove a2a3");
+        assert_eq!(xboard.engine_colour, Some(Colour::Black));
+        assert_eq!(xboard.game.get_move_history().len(), 4);
+
+        let xboard = session("new\nforce\nusermove e2e4\nplayother");
+        assert_eq!(xboard.engine_colour, Some(Colour::White));
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let lines = run("new\nusermove e2e5\nusermove e2\nusermove e7e8x\n");
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.starts_with("Illegal move (")));
+        assert!(lines[0].ends_with("): e2e5"));
+    }
+
+    #[test]
+    fn setboard_and_results() {
+        let lines = run("setboard 6k1/5ppp/8/8/8/8/8/3RK3 w - - 0 1\nsd 3\npost\ngo\n");
+        assert!(lines[0].starts_with("1 "));
+        assert_eq!(engine_move(&lines), "d1d8");
+        assert_eq!(lines.last().unwrap(), "1-0 {white wins by checkmate}");
+
+        // the user can end the game too
+        let lines = run("setboard 6k1/5ppp/8/8/8/8/8/3RK3 w - - 0 1\nforce\nusermove d1d8\n");
+        assert_eq!(lines, ["1-0 {white wins by checkmate}"]);
+
+        let lines = run("setboard 8/8/8/8\n");
+        assert_eq!(lines, ["tellusererror Illegal position"]);
+    }
+
+    #[test]
+    fn undo_and_remove() {
+        let xboard = session("new\nforce\nusermove e2e4\nusermove e7e5\nusermove g1f3\nundo");
+        assert_eq!(xboard.game.get_move_history().len(), 2);
+        let xboard = session("new\nforce\nusermove e2e4\nusermove e7e5\nremove");
+        assert_eq!(xboard.game.to_fen(), Game::new().to_fen());
+    }
+
+    #[test]
+    fn time_controls() {
+        let xboard = session("level 40 5 0\nsd 4");
+        assert_eq!(
+            xboard.limits(),
+            SearchLimits {
+                depth: Some(4),
+                nodes: None,
+                time: Some(Duration::from_millis(7500)),
+            }
+        );
+
+        // the clock from the GUI and the increment
+        let xboard = session("level 0 2:30 2\ntime 6000");
+        assert_eq!(
+            xboard.limits().time,
+            Some(Duration::from_secs(2) + Duration::from_secs(1))
+        );
+
+        let xboard = session("st 0.5");
+        assert_eq!(xboard.limits().time, Some(Duration::from_millis(500)));
+
+        assert_eq!(parse_minutes("0:30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_minutes("5"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_minutes("x"), None);
+        let lines = run("level 40 x 0\n");
+        assert_eq!(lines, ["Error (bad level): level 40 x 0"]);
+    }
+
+    #[test]
+    fn times_out_of_range() {
+        let lines = run("st 1e30\nst -1\nlevel 40 5 1e30\ntime 18446744073709551615\n");
+        assert_eq!(
+            lines,
+            [
+                "Error (bad time): st 1e30",
+                "Error (bad time): st -1",
+                "Error (bad time): level 40 5 1e30",
+                "Error (bad time): time 18446744073709551615",
+            ]
+        );
+        assert_eq!(parse_minutes("307445734561825861"), None);
+    }
+}
@@ -0,0 +1,199 @@
+//! Zobrist hashing: every piece on every square, the side to move, each castling right and each
+//! en passant file has a fixed random key, and a position's hash is all its keys xor'ed together.
+//! Making or taking back a move only has to xor the keys that changed.
+
+use crate::bitboard::{self, piece_index, Positions};
+use crate::{Colour, Game, Piece};
+
+struct Keys {
+    /// indexed by colour, kind of piece (as in `Bitboards`) and position
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    /// white king side, white queen side, black king side, black queen side
+    castling: [u64; 4],
+    en_passant: [u64; 8],
+}
+
+/// The keys come from a fixed seed, so hashes stay the same between runs and versions.
+static KEYS: Keys = generate_keys();
+
+/// splitmix64, small and good enough to fill the key tables at compile time
+const fn next_key(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut key = *state;
+    key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    key ^ (key >> 31)
+}
+
+const fn generate_keys() -> Keys {
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    let mut keys = Keys {
+        pieces: [[[0; 64]; 6]; 2],
+        black_to_move: 0,
+        castling: [0; 4],
+        en_passant: [0; 8],
+    };
+
+    let mut colour = 0;
+    while colour < 2 {
+        let mut kind = 0;
+        while kind < 6 {
+            let mut position = 0;
+            while position < 64 {
+                keys.pieces[colour][kind][position] = next_key(&mut state);
+                position += 1;
+            }
+            kind += 1;
+        }
+        colour += 1;
+    }
+    keys.black_to_move = next_key(&mut state);
+    let mut i = 0;
+    while i < 4 {
+        keys.castling[i] = next_key(&mut state);
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        keys.en_passant[i] = next_key(&mut state);
+        i += 1;
+    }
+    keys
+}
+
+/// the key of a piece standing on a position
+pub(crate) fn piece_key(piece: Piece, position: usize) -> u64 {
+    let (colour, kind) = piece_index(piece);
+    KEYS.pieces[colour][kind][position]
+}
+
+/// the key that is xor'ed in while black is to move
+pub(crate) fn black_to_move_key() -> u64 {
+    KEYS.black_to_move
+}
+
+impl Game {
+    /// Returns the Zobrist hash of the position, a 64 bit number that is the same for the same
+    /// pieces on the same squares, side to move, castling rights and possible en passant capture.
+    /// Different positions almost always get different hashes, so it can be used as a key for
+    /// caches and opening books.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// works out the hash of the position from scratch, rather than from the last move
+    pub(crate) fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (position, piece) in self.board.iter().enumerate() {
+            if let Some(piece) = piece {
+                hash ^= piece_key(*piece, position);
+            }
+        }
+        if self.active_colour == Colour::Black {
+            hash ^= black_to_move_key();
+        }
+        hash ^ self.castling_key() ^ self.en_passant_key()
+    }
+
+    /// the keys of the castling rights both colours still have
+    pub(crate) fn castling_key(&self) -> u64 {
+        [
+            self.white_castling.king_side,
+            self.white_castling.queen_side,
+            self.black_castling.king_side,
+            self.black_castling.queen_side,
+        ]
+        .iter()
+        .zip(KEYS.castling.iter())
+        .filter(|(right, _)| **right)
+        .fold(0, |hash, (_, key)| hash ^ key)
+    }
+
+    /// the key of the en passant file, but only when a pawn of the side to move can actually
+    /// make the capture, so the same position always gets the same hash
+    pub(crate) fn en_passant_key(&self) -> u64 {
+        let square = match self.en_passant {
+            Some(square) => square as usize,
+            None => return 0,
+        };
+        let other = match self.active_colour {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        };
+
+        // the pawns that could take on the square are the ones a pawn there would attack
+        let pawns = bitboard::pawn_attacks(square, other)
+            & self.bitboards.pieces(Piece::Pawn(self.active_colour));
+        if Positions(pawns)
+            .any(|pawn| self.legal_targets(pawn, self.active_colour) & (1 << square) != 0)
+        {
+            KEYS.en_passant[square % 8]
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, MoveList};
+
+    /// plays every move sequence up to `depth` and checks the hash after each move and take back
+    fn check_hashes(game: &mut Game, depth: u32) {
+        assert_eq!(game.hash(), game.compute_hash(), "{}", game.to_fen());
+        if depth == 0 {
+            return;
+        }
+
+        let mut moves = MoveList::new();
+        game.generate_moves(&mut moves);
+        for &mv in moves.iter() {
+            let before = game.hash();
+            game.apply_move(mv);
+            check_hashes(game, depth - 1);
+            game.take_back();
+            assert_eq!(game.hash(), before);
+        }
+    }
+
+    #[test]
+    fn incremental_hash_matches() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // castling, en passant and promotions
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            check_hashes(&mut game, 3);
+        }
+    }
+
+    #[test]
+    fn transpositions() {
+        let mut game = Game::new();
+        let start = game.hash();
+        for san in ["Nf3", "Nf6", "Nc3"] {
+            game.make_san_move(san);
+        }
+        let mut other = Game::new();
+        for san in ["Nc3", "Nf6", "Nf3"] {
+            other.make_san_move(san);
+        }
+        assert_eq!(game.hash(), other.hash());
+        assert_ne!(game.hash(), start);
+
+        // the same position from a FEN
+        let from_fen = Game::from_fen(&game.to_fen()).unwrap();
+        assert_eq!(from_fen.hash(), game.hash());
+
+        // the side to move and castling rights are part of the position
+        let white = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let black = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1").unwrap();
+        let no_castling = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_ne!(white.hash(), black.hash());
+        assert_ne!(white.hash(), no_castling.hash());
+    }
+}